@@ -5,9 +5,44 @@
 #include "sqlite3ext.h"
 
 /**
+ * Registers every function and table function.
+ *
  * # Safety
  *
  * Should only be called by underlying SQLite C APIs,
  * like sqlite3_auto_extension and sqlite3_cancel_auto_extension.
  */
 unsigned int sqlite3_regex_init(sqlite3 *db, char **pz_err_msg, sqlite3_api_routines *p_api);
+
+/**
+ * Registers only pure scalar, aggregate and window functions, leaving out the
+ * ones that read or write the database, create collations or call other SQL
+ * functions.
+ *
+ * # Safety
+ *
+ * Should only be called by underlying SQLite C APIs,
+ * like sqlite3_auto_extension and sqlite3_cancel_auto_extension.
+ */
+unsigned int sqlite3_regex_scalar_init(sqlite3 *db, char **pz_err_msg, sqlite3_api_routines *p_api);
+
+/**
+ * Registers only table functions, including the ones that read files.
+ *
+ * # Safety
+ *
+ * Should only be called by underlying SQLite C APIs,
+ * like sqlite3_auto_extension and sqlite3_cancel_auto_extension.
+ */
+unsigned int sqlite3_regex_tables_init(sqlite3 *db, char **pz_err_msg, sqlite3_api_routines *p_api);
+
+/**
+ * Registers pure functions and the table functions that don't touch the
+ * filesystem.
+ *
+ * # Safety
+ *
+ * Should only be called by underlying SQLite C APIs,
+ * like sqlite3_auto_extension and sqlite3_cancel_auto_extension.
+ */
+unsigned int sqlite3_regex_safe_init(sqlite3 *db, char **pz_err_msg, sqlite3_api_routines *p_api);
@@ -0,0 +1,136 @@
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder, MatchKind};
+use sqlite_loadable::prelude::*;
+use sqlite_loadable::{api, Error, Result};
+
+use crate::aggregate::Aggregate;
+use crate::utils::{result_ac_set, value_ac_set};
+
+/// Options that can be passed to ac_set_with_options() or ac_set_agg().
+/// 'i' matches ASCII letters case-insensitively, 'l' uses leftmost-longest
+/// instead of leftmost-first match semantics.
+const AC_SET_OPTIONS: &str = "il";
+
+/// A set of literal terms, compiled into an Aho-Corasick automaton.
+pub(crate) struct AcSet {
+    pub automaton: AhoCorasick,
+    pub terms: Vec<String>,
+}
+
+impl AcSet {
+    pub(crate) fn new(terms: Vec<String>, options: &str) -> Result<AcSet> {
+        if let Some(option) = options.chars().find(|c| !AC_SET_OPTIONS.contains(*c)) {
+            return Err(Error::new_message(
+                format!(
+                    "unknown ac_set option '{}', expected one of '{}'",
+                    option, AC_SET_OPTIONS
+                )
+                .as_str(),
+            ));
+        }
+        let match_kind = if options.contains('l') {
+            MatchKind::LeftmostLongest
+        } else {
+            MatchKind::LeftmostFirst
+        };
+        let automaton = AhoCorasickBuilder::new()
+            .ascii_case_insensitive(options.contains('i'))
+            .match_kind(match_kind)
+            .build(&terms);
+        Ok(AcSet { automaton, terms })
+    }
+}
+
+fn terms_from_values(values: &[*mut sqlite3_value]) -> Result<Vec<String>> {
+    let mut terms = Vec::with_capacity(values.len());
+    for value in values {
+        terms.push(api::value_text_notnull(value)?.to_owned());
+    }
+    Ok(terms)
+}
+
+/// ac_set(term1, ...)
+pub fn ac_set(context: *mut sqlite3_context, values: &[*mut sqlite3_value]) -> Result<()> {
+    result_ac_set(context, AcSet::new(terms_from_values(values)?, "")?);
+    Ok(())
+}
+
+/// ac_set_with_options(options, term1, ...)
+pub fn ac_set_with_options(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    let (options, terms) = values
+        .split_first()
+        .ok_or_else(|| Error::new_message("expected 1st argument as options"))?;
+    let options = api::value_text(options)?;
+    result_ac_set(context, AcSet::new(terms_from_values(terms)?, options)?);
+    Ok(())
+}
+
+/// ac_set_print(ac_set)
+pub fn ac_set_print(context: *mut sqlite3_context, values: &[*mut sqlite3_value]) -> Result<()> {
+    let set = value_ac_set(
+        values
+            .first()
+            .ok_or_else(|| Error::new_message("expected 1st argument as ac_set"))?,
+    )?;
+    let set = unsafe { &*set };
+    api::result_json(context, set.terms.clone().into())?;
+    Ok(())
+}
+
+/// ac_is_match(ac_set, text)
+pub fn ac_is_match(context: *mut sqlite3_context, values: &[*mut sqlite3_value]) -> Result<()> {
+    let set = value_ac_set(
+        values
+            .first()
+            .ok_or_else(|| Error::new_message("expected 1st argument as ac_set"))?,
+    )?;
+    let set = unsafe { &*set };
+    let text = api::value_text_notnull(
+        values
+            .get(1)
+            .ok_or_else(|| Error::new_message("expected 2nd argument as text"))?,
+    )?;
+    api::result_bool(context, set.automaton.is_match(text));
+    Ok(())
+}
+
+/// ac_set_agg(term [, options])
+#[derive(Default)]
+pub(crate) struct AcSetAgg {
+    terms: Vec<String>,
+    options: Option<String>,
+}
+
+impl Aggregate for AcSetAgg {
+    fn step(
+        &mut self,
+        _context: *mut sqlite3_context,
+        values: &[*mut sqlite3_value],
+    ) -> Result<()> {
+        if self.options.is_none() {
+            self.options = Some(match values.get(1) {
+                Some(value) => api::value_text(value)?.to_owned(),
+                None => String::new(),
+            });
+        }
+        let term = values
+            .first()
+            .ok_or_else(|| Error::new_message("expected 1st argument as term"))?;
+        if !api::value_is_null(term) {
+            self.terms.push(api::value_text(term)?.to_owned());
+        }
+        Ok(())
+    }
+
+    fn result(&self, context: *mut sqlite3_context) -> Result<()> {
+        if self.terms.is_empty() {
+            api::result_null(context);
+            return Ok(());
+        }
+        let options = self.options.as_deref().unwrap_or("");
+        result_ac_set(context, AcSet::new(self.terms.clone(), options)?);
+        Ok(())
+    }
+}
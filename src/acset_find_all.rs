@@ -0,0 +1,186 @@
+use sqlite_loadable::{
+    api,
+    table::{ConstraintOperator, IndexInfo, VTab, VTabArguments, VTabCursor},
+    BestIndexError, Result,
+};
+use sqlite_loadable::{prelude::*, Error};
+
+use std::{mem, os::raw::c_int};
+
+use crate::{
+    ext::{vtab_config, VTabConfig},
+    utils::value_ac_set,
+};
+
+static CREATE_SQL: &str =
+    "CREATE TABLE x(term_index int, start int, end int, match text, ac_set hidden, contents text hidden)";
+enum Columns {
+    TermIndex,
+    Start,
+    End,
+    Match,
+    AcSet,
+    Contents,
+}
+fn column(index: i32) -> Option<Columns> {
+    match index {
+        0 => Some(Columns::TermIndex),
+        1 => Some(Columns::Start),
+        2 => Some(Columns::End),
+        3 => Some(Columns::Match),
+        4 => Some(Columns::AcSet),
+        5 => Some(Columns::Contents),
+        _ => None,
+    }
+}
+
+#[repr(C)]
+pub struct AcFindAllTable {
+    /// must be first
+    base: sqlite3_vtab,
+}
+
+impl<'vtab> VTab<'vtab> for AcFindAllTable {
+    type Aux = ();
+    type Cursor = AcFindAllCursor;
+
+    fn connect(
+        db: *mut sqlite3,
+        _aux: Option<&Self::Aux>,
+        _args: VTabArguments,
+    ) -> Result<(String, AcFindAllTable)> {
+        let vtab = AcFindAllTable {
+            base: unsafe { mem::zeroed() },
+        };
+        vtab_config(db, VTabConfig::Innocuous)?;
+        Ok((CREATE_SQL.to_owned(), vtab))
+    }
+    fn destroy(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn best_index(&self, mut info: IndexInfo) -> core::result::Result<(), BestIndexError> {
+        let mut has_set = false;
+        let mut has_contents = false;
+        for mut constraint in info.constraints() {
+            match column(constraint.column_idx()) {
+                Some(Columns::AcSet) => {
+                    if constraint.usable() && constraint.op() == Some(ConstraintOperator::EQ) {
+                        constraint.set_omit(true);
+                        constraint.set_argv_index(1);
+                        has_set = true;
+                    } else {
+                        return Err(BestIndexError::Constraint);
+                    }
+                }
+                Some(Columns::Contents) => {
+                    if constraint.usable() && constraint.op() == Some(ConstraintOperator::EQ) {
+                        constraint.set_omit(true);
+                        constraint.set_argv_index(2);
+                        has_contents = true;
+                    } else {
+                        return Err(BestIndexError::Constraint);
+                    }
+                }
+                _ => (),
+            }
+        }
+        if !has_set || !has_contents {
+            return Err(BestIndexError::Error);
+        }
+        info.set_estimated_cost(100000.0);
+        info.set_estimated_rows(100000);
+        info.set_idxnum(2);
+
+        Ok(())
+    }
+
+    fn open(&mut self) -> Result<AcFindAllCursor> {
+        Ok(AcFindAllCursor::new())
+    }
+}
+
+#[repr(C)]
+pub struct AcFindAllCursor {
+    /// Base class. Must be first
+    base: sqlite3_vtab_cursor,
+    contents: String,
+    /// (term_index, start, end) of every match
+    matches: Vec<(usize, usize, usize)>,
+    rowid: usize,
+}
+impl AcFindAllCursor {
+    fn new() -> AcFindAllCursor {
+        let base: sqlite3_vtab_cursor = unsafe { mem::zeroed() };
+        AcFindAllCursor {
+            base,
+            contents: String::new(),
+            matches: vec![],
+            rowid: 0,
+        }
+    }
+}
+
+impl VTabCursor for AcFindAllCursor {
+    fn filter(
+        &mut self,
+        _idx_num: c_int,
+        _idx_str: Option<&str>,
+        values: &[*mut sqlite3_value],
+    ) -> Result<()> {
+        let set = value_ac_set(values.first().ok_or_else(|| {
+            Error::new_message("internal error: ac_set not passed into xFilter")
+        })?)?;
+        let set = unsafe { &*set };
+        let contents = api::value_text_notnull(values.get(1).ok_or_else(|| {
+            Error::new_message("internal error: contents not passed into xFilter")
+        })?)?;
+
+        self.matches = set
+            .automaton
+            .find_iter(contents)
+            .map(|m| (m.pattern(), m.start(), m.end()))
+            .collect();
+        self.contents = contents.to_owned();
+        self.rowid = 0;
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.rowid += 1;
+        Ok(())
+    }
+
+    fn eof(&self) -> bool {
+        self.rowid >= self.matches.len()
+    }
+
+    fn column(&self, context: *mut sqlite3_context, i: c_int) -> Result<()> {
+        let (term_index, start, end) = self.matches.get(self.rowid).ok_or_else(|| {
+            Error::new_message(
+                "sqlite-regex internal error: self.rowid greater than matches result",
+            )
+        })?;
+        match column(i) {
+            Some(Columns::TermIndex) => {
+                api::result_int64(context, *term_index as i64);
+            }
+            Some(Columns::Start) => {
+                api::result_int64(context, *start as i64);
+            }
+            Some(Columns::End) => {
+                api::result_int64(context, *end as i64);
+            }
+            Some(Columns::Match) => {
+                api::result_text(context, &self.contents[*start..*end])?;
+            }
+            Some(Columns::AcSet) | Some(Columns::Contents) => (),
+            None => (),
+        }
+        Ok(())
+    }
+
+    fn rowid(&self) -> Result<i64> {
+        Ok(self.rowid as i64)
+    }
+}
@@ -0,0 +1,180 @@
+//! Aggregate and window function support. sqlite-loadable only wraps scalar
+//! functions, so this calls sqlite3_create_window_function() and
+//! sqlite3_aggregate_context() directly.
+
+use sqlite_loadable::prelude::*;
+use sqlite_loadable::{api, Error, Result};
+
+use crate::ext::{sqlite3_api, EXPECT_MESSAGE};
+
+use std::{
+    ffi::CString,
+    mem,
+    os::raw::{c_int, c_void},
+    ptr, slice,
+};
+
+/// State for one group of an aggregate function. A new value is created with
+/// `Default` on the first row of every group.
+pub(crate) trait Aggregate: Default {
+    /// Adds a row to the group.
+    fn step(&mut self, context: *mut sqlite3_context, values: &[*mut sqlite3_value]) -> Result<()>;
+    /// Returns the current result of the group.
+    fn result(&self, context: *mut sqlite3_context) -> Result<()>;
+}
+
+/// An aggregate that can also be used as a window function with a sliding frame.
+pub(crate) trait WindowAggregate: Aggregate {
+    /// Removes the oldest row previously added with `step`.
+    fn inverse(
+        &mut self,
+        context: *mut sqlite3_context,
+        values: &[*mut sqlite3_value],
+    ) -> Result<()>;
+}
+
+fn report_error(context: *mut sqlite3_context, result: Result<()>) {
+    if let Err(err) = result {
+        if api::result_error(context, &err.result_error_message()).is_err() {
+            api::result_error_code(context, 2); // SQLITE_INTERNAL
+        }
+    }
+}
+
+/// Returns the slot sqlite3_aggregate_context() allocates for the group's
+/// state, or null if `create` is false and no rows were added yet.
+unsafe fn state_slot<A>(context: *mut sqlite3_context, create: bool) -> *mut *mut A {
+    let size = if create {
+        mem::size_of::<*mut A>() as c_int
+    } else {
+        0
+    };
+    (sqlite3_api().aggregate_context.expect(EXPECT_MESSAGE))(context, size).cast::<*mut A>()
+}
+
+unsafe extern "C" fn x_step<A: Aggregate>(
+    context: *mut sqlite3_context,
+    argc: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    let slot = state_slot::<A>(context, true);
+    if slot.is_null() {
+        api::result_error_code(context, 7); // SQLITE_NOMEM
+        return;
+    }
+    if (*slot).is_null() {
+        *slot = Box::into_raw(Box::<A>::default());
+    }
+    let args = slice::from_raw_parts(argv, argc as usize);
+    report_error(context, (**slot).step(context, args));
+}
+
+unsafe extern "C" fn x_inverse<A: WindowAggregate>(
+    context: *mut sqlite3_context,
+    argc: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    let slot = state_slot::<A>(context, false);
+    if slot.is_null() || (*slot).is_null() {
+        return;
+    }
+    let args = slice::from_raw_parts(argv, argc as usize);
+    report_error(context, (**slot).inverse(context, args));
+}
+
+unsafe extern "C" fn x_value<A: Aggregate>(context: *mut sqlite3_context) {
+    let slot = state_slot::<A>(context, false);
+    let result = if slot.is_null() || (*slot).is_null() {
+        A::default().result(context)
+    } else {
+        (**slot).result(context)
+    };
+    report_error(context, result);
+}
+
+unsafe extern "C" fn x_final<A: Aggregate>(context: *mut sqlite3_context) {
+    let slot = state_slot::<A>(context, false);
+    if slot.is_null() || (*slot).is_null() {
+        report_error(context, A::default().result(context));
+        return;
+    }
+    let state = Box::from_raw(*slot);
+    *slot = ptr::null_mut();
+    report_error(context, state.result(context));
+}
+
+type XStep = unsafe extern "C" fn(*mut sqlite3_context, c_int, *mut *mut sqlite3_value);
+type XFinal = unsafe extern "C" fn(*mut sqlite3_context);
+
+#[allow(clippy::too_many_arguments)]
+fn create_window_function(
+    db: *mut sqlite3,
+    name: &str,
+    num_args: c_int,
+    func_flags: FunctionFlags,
+    x_step: XStep,
+    x_final: XFinal,
+    x_value: Option<XFinal>,
+    x_inverse: Option<XStep>,
+) -> Result<()> {
+    let cname = CString::new(name)?;
+    let result = unsafe {
+        (sqlite3_api().create_window_function.expect(EXPECT_MESSAGE))(
+            db,
+            cname.as_ptr(),
+            num_args,
+            func_flags.bits(),
+            ptr::null_mut::<c_void>(),
+            Some(x_step),
+            Some(x_final),
+            x_value,
+            x_inverse,
+            None,
+        )
+    };
+    if result != 0 {
+        return Err(Error::new_message(
+            format!("Error defining aggregate function {}", name).as_str(),
+        ));
+    }
+    Ok(())
+}
+
+/// Defines an aggregate function, where `A` holds the state of each group.
+pub(crate) fn define_aggregate_function<A: Aggregate>(
+    db: *mut sqlite3,
+    name: &str,
+    num_args: c_int,
+    func_flags: FunctionFlags,
+) -> Result<()> {
+    create_window_function(
+        db,
+        name,
+        num_args,
+        func_flags,
+        x_step::<A>,
+        x_final::<A>,
+        None,
+        None,
+    )
+}
+
+/// Defines an aggregate function that can also be used as a window function
+/// with `OVER (...)`, including frames that slide forward.
+pub(crate) fn define_window_function<A: WindowAggregate>(
+    db: *mut sqlite3,
+    name: &str,
+    num_args: c_int,
+    func_flags: FunctionFlags,
+) -> Result<()> {
+    create_window_function(
+        db,
+        name,
+        num_args,
+        func_flags,
+        x_step::<A>,
+        x_final::<A>,
+        Some(x_value::<A>),
+        Some(x_inverse::<A>),
+    )
+}
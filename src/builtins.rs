@@ -0,0 +1,269 @@
+use regex::Regex;
+use sqlite_loadable::{
+    api,
+    table::{IndexInfo, VTab, VTabArguments, VTabCursor},
+    BestIndexError, Result,
+};
+use sqlite_loadable::{prelude::*, Error};
+
+use std::{
+    mem,
+    os::raw::{c_int, c_void},
+};
+
+use crate::ext::{vtab_config, VTabConfig};
+use crate::utils::{cleanup_regex, result_regex};
+
+pub(crate) struct Builtin {
+    pub name: &'static str,
+    pub pattern: &'static str,
+    pub description: &'static str,
+}
+
+/// Curated patterns available through regex_builtin(name). None of them are
+/// anchored, so they can find matches inside larger text. Digits use `[0-9]`
+/// rather than `\d`, which would also match non-ASCII digits.
+pub(crate) static BUILTINS: &[Builtin] = &[
+    Builtin {
+        name: "email",
+        pattern: r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}",
+        description: "Email address, like 'alex@example.com'",
+    },
+    Builtin {
+        name: "url",
+        pattern: r"(?i)\bhttps?://[^\s/$.?#][^\s]*",
+        description: "http:// or https:// URL, up to the next whitespace",
+    },
+    Builtin {
+        name: "domain",
+        pattern: r"\b(?:[A-Za-z0-9](?:[A-Za-z0-9-]{0,61}[A-Za-z0-9])?\.)+[A-Za-z]{2,63}\b",
+        description: "Domain name, like 'sub.example.com'",
+    },
+    Builtin {
+        name: "ipv4",
+        pattern: r"\b(?:(?:25[0-5]|2[0-4][0-9]|1[0-9]{2}|[1-9]?[0-9])\.){3}(?:25[0-5]|2[0-4][0-9]|1[0-9]{2}|[1-9]?[0-9])\b",
+        description: "IPv4 address in dotted-decimal notation, like '192.168.0.1'",
+    },
+    Builtin {
+        name: "ipv6",
+        pattern: concat!(
+            r"(?:(?:[0-9A-Fa-f]{1,4}:){7}[0-9A-Fa-f]{1,4}",
+            r"|[0-9A-Fa-f]{1,4}:(?::[0-9A-Fa-f]{1,4}){1,6}",
+            r"|(?:[0-9A-Fa-f]{1,4}:){1,2}(?::[0-9A-Fa-f]{1,4}){1,5}",
+            r"|(?:[0-9A-Fa-f]{1,4}:){1,3}(?::[0-9A-Fa-f]{1,4}){1,4}",
+            r"|(?:[0-9A-Fa-f]{1,4}:){1,4}(?::[0-9A-Fa-f]{1,4}){1,3}",
+            r"|(?:[0-9A-Fa-f]{1,4}:){1,5}(?::[0-9A-Fa-f]{1,4}){1,2}",
+            r"|(?:[0-9A-Fa-f]{1,4}:){1,6}:[0-9A-Fa-f]{1,4}",
+            r"|(?:[0-9A-Fa-f]{1,4}:){1,7}:",
+            r"|:(?:(?::[0-9A-Fa-f]{1,4}){1,7}|:))"
+        ),
+        description: "IPv6 address, full or '::' compressed, like '2001:db8::1'. Doesn't include zone indices or embedded IPv4",
+    },
+    Builtin {
+        name: "mac_address",
+        pattern: r"\b[0-9A-Fa-f]{2}(?:[:-][0-9A-Fa-f]{2}){5}\b",
+        description: "MAC address, separated by ':' or '-', like '00:1a:2b:3c:4d:5e'",
+    },
+    Builtin {
+        name: "uuid",
+        pattern: r"\b[0-9A-Fa-f]{8}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{12}\b",
+        description: "UUID in 8-4-4-4-12 hex format, any version",
+    },
+    Builtin {
+        name: "iso8601_date",
+        pattern: r"\b[0-9]{4}-(?:0[1-9]|1[0-2])-(?:0[1-9]|[12][0-9]|3[01])\b",
+        description: "ISO-8601 calendar date, like '2023-01-31'",
+    },
+    Builtin {
+        name: "iso8601_time",
+        pattern: r"\b(?:[01][0-9]|2[0-3]):[0-5][0-9](?::[0-5][0-9](?:\.[0-9]+)?)?\b",
+        description: "ISO-8601 time of day, with optional seconds and fraction, like '13:45:30.250'",
+    },
+    Builtin {
+        name: "iso8601_datetime",
+        pattern: r"\b[0-9]{4}-(?:0[1-9]|1[0-2])-(?:0[1-9]|[12][0-9]|3[01])[T ](?:[01][0-9]|2[0-3]):[0-5][0-9](?::[0-5][0-9](?:\.[0-9]+)?)?(?:Z|[+-](?:[01][0-9]|2[0-3]):?[0-5][0-9])?",
+        description: "ISO-8601 date and time, with optional UTC offset, like '2023-01-31T13:45:30Z'",
+    },
+    Builtin {
+        name: "semver",
+        pattern: r"\b(?P<major>0|[1-9][0-9]*)\.(?P<minor>0|[1-9][0-9]*)\.(?P<patch>0|[1-9][0-9]*)(?:-(?P<prerelease>(?:0|[1-9][0-9]*|[0-9]*[a-zA-Z-][0-9a-zA-Z-]*)(?:\.(?:0|[1-9][0-9]*|[0-9]*[a-zA-Z-][0-9a-zA-Z-]*))*))?(?:\+(?P<buildmetadata>[0-9a-zA-Z-]+(?:\.[0-9a-zA-Z-]+)*))?",
+        description: "Semantic version, like '1.2.3-beta.1+build.5', with major/minor/patch/prerelease/buildmetadata groups",
+    },
+    Builtin {
+        name: "us_phone",
+        pattern: r"(?:\+?1[-. ]?)?(?:\([2-9][0-9]{2}\)|[2-9][0-9]{2})[-. ]?[2-9][0-9]{2}[-. ]?[0-9]{4}\b",
+        description: "US/NANP phone number, like '(555) 555-1234' or '+1 555.555.1234'",
+    },
+    Builtin {
+        name: "us_zip",
+        pattern: r"\b[0-9]{5}(?:-[0-9]{4})?\b",
+        description: "US ZIP code, with optional ZIP+4, like '94103-1234'",
+    },
+    Builtin {
+        name: "us_ssn",
+        pattern: r"\b[0-9]{3}-[0-9]{2}-[0-9]{4}\b",
+        description: "US social security number in 123-45-6789 format",
+    },
+    Builtin {
+        name: "credit_card",
+        pattern: r"\b[0-9](?:[ -]?[0-9]){12,18}\b",
+        description: "Credit-card-like run of 13 to 19 digits, optionally separated by single spaces or dashes. Doesn't check the Luhn checksum",
+    },
+    Builtin {
+        name: "hex_color",
+        pattern: r"#(?:[0-9A-Fa-f]{8}|[0-9A-Fa-f]{6}|[0-9A-Fa-f]{3,4})\b",
+        description: "CSS hex color, like '#fff' or '#1e90ffcc'",
+    },
+    Builtin {
+        name: "number",
+        pattern: r"[-+]?(?:[0-9]+(?:\.[0-9]*)?|\.[0-9]+)(?:[eE][-+]?[0-9]+)?",
+        description: "Integer or decimal number, with optional sign and exponent, like '-1.5e10'",
+    },
+];
+
+pub(crate) fn builtin(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|b| b.name == name)
+}
+
+/// regex_builtin(name)
+pub fn regex_builtin(context: *mut sqlite3_context, values: &[*mut sqlite3_value]) -> Result<()> {
+    let auxdata = api::auxdata_get(context, 0);
+    let regex = if !auxdata.is_null() {
+        unsafe { &*auxdata.cast::<Regex>() }.clone()
+    } else {
+        let name = api::value_text_notnull(
+            values
+                .first()
+                .ok_or_else(|| Error::new_message("expected 1st argument as name"))?,
+        )?;
+        let builtin = builtin(name).ok_or_else(|| {
+            Error::new_message(
+                format!(
+                    "'{}' is not a builtin pattern, see regex_builtins for a list",
+                    name
+                )
+                .as_str(),
+            )
+        })?;
+        let regex = Regex::new(builtin.pattern).map_err(|err| {
+            Error::new_message(format!("internal error: invalid builtin pattern: {}", err).as_str())
+        })?;
+        api::auxdata_set(
+            context,
+            0,
+            Box::into_raw(Box::new(regex.clone())).cast::<c_void>(),
+            Some(cleanup_regex),
+        );
+        regex
+    };
+    result_regex(context, regex);
+    Ok(())
+}
+
+static CREATE_SQL: &str = "CREATE TABLE x(name text, pattern text, description text)";
+enum Columns {
+    Name,
+    Pattern,
+    Description,
+}
+fn column(index: i32) -> Option<Columns> {
+    match index {
+        0 => Some(Columns::Name),
+        1 => Some(Columns::Pattern),
+        2 => Some(Columns::Description),
+        _ => None,
+    }
+}
+
+#[repr(C)]
+pub struct RegexBuiltinsTable {
+    /// must be first
+    base: sqlite3_vtab,
+}
+
+impl<'vtab> VTab<'vtab> for RegexBuiltinsTable {
+    type Aux = ();
+    type Cursor = RegexBuiltinsCursor;
+
+    fn connect(
+        db: *mut sqlite3,
+        _aux: Option<&Self::Aux>,
+        _args: VTabArguments,
+    ) -> Result<(String, RegexBuiltinsTable)> {
+        let base: sqlite3_vtab = unsafe { mem::zeroed() };
+        let vtab = RegexBuiltinsTable { base };
+        vtab_config(db, VTabConfig::Innocuous)?;
+        Ok((CREATE_SQL.to_owned(), vtab))
+    }
+    fn destroy(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn best_index(&self, mut info: IndexInfo) -> core::result::Result<(), BestIndexError> {
+        info.set_estimated_cost(BUILTINS.len() as f64);
+        info.set_estimated_rows(BUILTINS.len() as i64);
+        info.set_idxnum(1);
+        Ok(())
+    }
+
+    fn open(&mut self) -> Result<RegexBuiltinsCursor> {
+        Ok(RegexBuiltinsCursor::new())
+    }
+}
+
+#[repr(C)]
+pub struct RegexBuiltinsCursor {
+    /// Base class. Must be first
+    base: sqlite3_vtab_cursor,
+    rowid: usize,
+}
+impl RegexBuiltinsCursor {
+    fn new() -> RegexBuiltinsCursor {
+        let base: sqlite3_vtab_cursor = unsafe { mem::zeroed() };
+        RegexBuiltinsCursor { base, rowid: 0 }
+    }
+}
+
+impl VTabCursor for RegexBuiltinsCursor {
+    fn filter(
+        &mut self,
+        _idx_num: c_int,
+        _idx_str: Option<&str>,
+        _values: &[*mut sqlite3_value],
+    ) -> Result<()> {
+        self.rowid = 0;
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.rowid += 1;
+        Ok(())
+    }
+
+    fn eof(&self) -> bool {
+        self.rowid >= BUILTINS.len()
+    }
+
+    fn column(&self, context: *mut sqlite3_context, i: c_int) -> Result<()> {
+        let builtin = BUILTINS.get(self.rowid).ok_or_else(|| {
+            Error::new_message("sqlite-regex internal error: self.rowid greater than builtins")
+        })?;
+        match column(i) {
+            Some(Columns::Name) => {
+                api::result_text(context, builtin.name)?;
+            }
+            Some(Columns::Pattern) => {
+                api::result_text(context, builtin.pattern)?;
+            }
+            Some(Columns::Description) => {
+                api::result_text(context, builtin.description)?;
+            }
+            None => (),
+        }
+        Ok(())
+    }
+
+    fn rowid(&self) -> Result<i64> {
+        Ok(self.rowid as i64)
+    }
+}
@@ -0,0 +1,240 @@
+use regex::Regex;
+use regex_syntax::hir::{self, Hir, HirKind};
+use sqlite_loadable::{
+    api,
+    table::{ConstraintOperator, IndexInfo, VTab, VTabArguments, VTabCursor},
+    BestIndexError, Result,
+};
+use sqlite_loadable::{prelude::*, Error};
+
+use std::{mem, os::raw::c_int};
+
+use crate::ext::{vtab_config, VTabConfig};
+use crate::utils::{cleanup_regex_value_cached, regex_from_value_or_cache, value_regex_owned};
+
+/// regex_captures_len(pattern)
+pub fn regex_captures_len(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    let (regex, input_type) = regex_from_value_or_cache(context, values, 0)?;
+    let regex = unsafe { &mut *regex };
+    api::result_int64(context, regex.captures_len() as i64);
+    cleanup_regex_value_cached(context, regex, input_type);
+    Ok(())
+}
+
+/// regex_group_index(pattern, name)
+pub fn regex_group_index(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    let (regex, input_type) = regex_from_value_or_cache(context, values, 0)?;
+    let regex = unsafe { &mut *regex };
+    let name = api::value_text_notnull(
+        values
+            .get(1)
+            .ok_or_else(|| Error::new_message("expected 2nd argument as group name"))?,
+    )?;
+    match regex.capture_names().position(|n| n == Some(name)) {
+        Some(index) => api::result_int64(context, index as i64),
+        None => api::result_null(context),
+    }
+    cleanup_regex_value_cached(context, regex, input_type);
+    Ok(())
+}
+
+/// Marks every capture group that doesn't have to participate in a match,
+/// because it's inside an alternation branch or a repetition that can match
+/// zero times. Indexed by group index, group 0 is never optional.
+fn optional_groups(regex: &Regex) -> Result<Vec<bool>> {
+    let hir = regex_syntax::Parser::new()
+        .parse(regex.as_str())
+        .map_err(|err| Error::new_message(format!("Error parsing regex: {}", err).as_str()))?;
+    let mut optional = vec![false; regex.captures_len()];
+    mark_optional_groups(&hir, false, &mut optional);
+    Ok(optional)
+}
+
+fn mark_optional_groups(node: &Hir, inside_optional: bool, optional: &mut Vec<bool>) {
+    match node.kind() {
+        HirKind::Group(group) => {
+            let index = match &group.kind {
+                hir::GroupKind::CaptureIndex(index) => Some(*index),
+                hir::GroupKind::CaptureName { index, .. } => Some(*index),
+                hir::GroupKind::NonCapturing => None,
+            };
+            if let Some(flag) = index.and_then(|index| optional.get_mut(index as usize)) {
+                *flag = inside_optional;
+            }
+            mark_optional_groups(&group.hir, inside_optional, optional);
+        }
+        HirKind::Repetition(repetition) => {
+            let can_skip = match &repetition.kind {
+                hir::RepetitionKind::ZeroOrOne | hir::RepetitionKind::ZeroOrMore => true,
+                hir::RepetitionKind::OneOrMore => false,
+                hir::RepetitionKind::Range(hir::RepetitionRange::Exactly(n))
+                | hir::RepetitionKind::Range(hir::RepetitionRange::AtLeast(n))
+                | hir::RepetitionKind::Range(hir::RepetitionRange::Bounded(n, _)) => *n == 0,
+            };
+            mark_optional_groups(&repetition.hir, inside_optional || can_skip, optional);
+        }
+        HirKind::Concat(nodes) => {
+            for node in nodes {
+                mark_optional_groups(node, inside_optional, optional);
+            }
+        }
+        HirKind::Alternation(nodes) => {
+            for node in nodes {
+                mark_optional_groups(node, true, optional);
+            }
+        }
+        _ => (),
+    }
+}
+
+static CREATE_SQL: &str =
+    "CREATE TABLE x(group_index int, name text, optional int, pattern hidden)";
+enum Columns {
+    GroupIndex,
+    Name,
+    Optional,
+    Pattern,
+}
+fn column(index: i32) -> Option<Columns> {
+    match index {
+        0 => Some(Columns::GroupIndex),
+        1 => Some(Columns::Name),
+        2 => Some(Columns::Optional),
+        3 => Some(Columns::Pattern),
+        _ => None,
+    }
+}
+
+#[repr(C)]
+pub struct RegexCaptureNamesTable {
+    /// must be first
+    base: sqlite3_vtab,
+}
+
+impl<'vtab> VTab<'vtab> for RegexCaptureNamesTable {
+    type Aux = ();
+    type Cursor = RegexCaptureNamesCursor;
+
+    fn connect(
+        db: *mut sqlite3,
+        _aux: Option<&Self::Aux>,
+        _args: VTabArguments,
+    ) -> Result<(String, RegexCaptureNamesTable)> {
+        let base: sqlite3_vtab = unsafe { mem::zeroed() };
+        let vtab = RegexCaptureNamesTable { base };
+        vtab_config(db, VTabConfig::Innocuous)?;
+        Ok((CREATE_SQL.to_owned(), vtab))
+    }
+    fn destroy(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn best_index(&self, mut info: IndexInfo) -> core::result::Result<(), BestIndexError> {
+        let mut has_pattern = false;
+        for mut constraint in info.constraints() {
+            if let Some(Columns::Pattern) = column(constraint.column_idx()) {
+                if constraint.usable() && constraint.op() == Some(ConstraintOperator::EQ) {
+                    constraint.set_omit(true);
+                    constraint.set_argv_index(1);
+                    has_pattern = true;
+                } else {
+                    return Err(BestIndexError::Constraint);
+                }
+            }
+        }
+        if !has_pattern {
+            return Err(BestIndexError::Error);
+        }
+        info.set_estimated_cost(10.0);
+        info.set_estimated_rows(10);
+        info.set_idxnum(1);
+
+        Ok(())
+    }
+
+    fn open(&mut self) -> Result<RegexCaptureNamesCursor> {
+        Ok(RegexCaptureNamesCursor::new())
+    }
+}
+
+#[repr(C)]
+pub struct RegexCaptureNamesCursor {
+    /// Base class. Must be first
+    base: sqlite3_vtab_cursor,
+    names: Vec<Option<String>>,
+    optional: Vec<bool>,
+    curr: usize,
+}
+impl RegexCaptureNamesCursor {
+    fn new() -> RegexCaptureNamesCursor {
+        let base: sqlite3_vtab_cursor = unsafe { mem::zeroed() };
+        RegexCaptureNamesCursor {
+            base,
+            names: vec![],
+            optional: vec![],
+            curr: 0,
+        }
+    }
+}
+
+impl VTabCursor for RegexCaptureNamesCursor {
+    fn filter(
+        &mut self,
+        _idx_num: c_int,
+        _idx_str: Option<&str>,
+        values: &[*mut sqlite3_value],
+    ) -> Result<()> {
+        let regex = value_regex_owned(
+            values
+                .first()
+                .ok_or_else(|| Error::new_message("expected 1st argument as regex"))?,
+        )?;
+        self.names = regex
+            .capture_names()
+            .map(|name| name.map(|name| name.to_owned()))
+            .collect();
+        self.optional = optional_groups(&regex)?;
+        self.curr = 0;
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.curr += 1;
+        Ok(())
+    }
+
+    fn eof(&self) -> bool {
+        self.curr >= self.names.len()
+    }
+
+    fn column(&self, context: *mut sqlite3_context, i: c_int) -> Result<()> {
+        match column(i) {
+            Some(Columns::GroupIndex) => {
+                api::result_int64(context, self.curr as i64);
+            }
+            Some(Columns::Name) => match self.names.get(self.curr) {
+                Some(Some(name)) => api::result_text(context, name)?,
+                _ => api::result_null(context),
+            },
+            Some(Columns::Optional) => {
+                api::result_bool(
+                    context,
+                    self.optional.get(self.curr).copied().unwrap_or(false),
+                );
+            }
+            Some(Columns::Pattern) => (),
+            None => (),
+        }
+        Ok(())
+    }
+
+    fn rowid(&self) -> Result<i64> {
+        Ok(self.curr as i64)
+    }
+}
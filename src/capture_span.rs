@@ -0,0 +1,150 @@
+//! regex_capture_start(), regex_capture_end() and regex_capture_span(), the
+//! positions of a capture group rather than its text. Offsets are 0-based, with
+//! an exclusive end, in bytes or in characters.
+
+use serde_json::json;
+use sqlite_loadable::prelude::*;
+use sqlite_loadable::{api, Error, Result};
+
+use crate::functions::capture_match;
+use crate::utils::{
+    cleanup_regex_value_cached, find_capture_group, regex_from_value_or_cache, value_group,
+    value_regex_captures,
+};
+
+#[derive(Clone, Copy)]
+enum Position {
+    Start,
+    End,
+    Span,
+}
+
+/// Reads the optional `unit` argument. NULL means bytes.
+fn value_chars_unit(value: Option<&*mut sqlite3_value>) -> Result<bool> {
+    let value = match value {
+        Some(value) if !api::value_is_null(value) => value,
+        _ => return Ok(false),
+    };
+    match api::value_text(value)? {
+        "byte" => Ok(false),
+        "char" => Ok(true),
+        unit => Err(Error::new_message(
+            format!("unknown offset unit '{}', expected 'byte' or 'char'", unit).as_str(),
+        )),
+    }
+}
+
+/// The start and end of a group, in bytes and in characters.
+struct Offsets {
+    bytes: (usize, usize),
+    chars: (usize, usize),
+}
+
+impl Offsets {
+    fn new(start: usize, char_start: usize, value: &str) -> Offsets {
+        Offsets {
+            bytes: (start, start + value.len()),
+            chars: (char_start, char_start + value.chars().count()),
+        }
+    }
+}
+
+/// Finds the group in either form of arguments, returning None when it didn't
+/// participate, and whether offsets should be in characters.
+fn capture_offsets(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<(Option<Offsets>, bool)> {
+    let first = values
+        .first()
+        .ok_or_else(|| Error::new_message("expected 1st argument as pattern or captures"))?;
+
+    if let Ok(captures) = value_regex_captures(first) {
+        // (captures, group [, unit])
+        if values.len() > 3 {
+            return Err(Error::new_message(
+                "expected at most 3 arguments with a captures object",
+            ));
+        }
+        let group =
+            value_group(values.get(1).ok_or_else(|| {
+                Error::new_message("expected 2nd argument as group index or name")
+            })?)?;
+        let chars = value_chars_unit(values.get(2))?;
+        let offsets = find_capture_group(unsafe { &*captures }, group).and_then(|capture| {
+            capture
+                .value
+                .as_ref()
+                .map(|value| Offsets::new(capture.start, capture.char_start, value))
+        });
+        return Ok((offsets, chars));
+    }
+
+    // (pattern, text, group [, unit])
+    let content = api::value_text_notnull(
+        values
+            .get(1)
+            .ok_or_else(|| Error::new_message("expected 2nd argument as contents"))?,
+    )?;
+    let group = value_group(
+        values
+            .get(2)
+            .ok_or_else(|| Error::new_message("expected 3rd argument as group index or name"))?,
+    )?;
+    let chars = value_chars_unit(values.get(3))?;
+    let (regex, input_type) = regex_from_value_or_cache(context, values, 0)?;
+    let offsets = unsafe { &*regex }.captures(content).and_then(|captures| {
+        capture_match(&captures, group)
+            .map(|m| Offsets::new(m.start(), content[..m.start()].chars().count(), m.as_str()))
+    });
+    cleanup_regex_value_cached(context, regex, input_type);
+    Ok((offsets, chars))
+}
+
+fn capture_position(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+    position: Position,
+) -> Result<()> {
+    let (start, end) = match capture_offsets(context, values)? {
+        (Some(offsets), false) => offsets.bytes,
+        (Some(offsets), true) => offsets.chars,
+        (None, _) => {
+            api::result_null(context);
+            return Ok(());
+        }
+    };
+    match position {
+        Position::Start => api::result_int64(context, start as i64),
+        Position::End => api::result_int64(context, end as i64),
+        Position::Span => api::result_json(context, json!([start, end]))?,
+    }
+    Ok(())
+}
+
+/// regex_capture_start(pattern, text, group [, unit])
+/// regex_capture_start(captures, group [, unit])
+pub fn regex_capture_start(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    capture_position(context, values, Position::Start)
+}
+
+/// regex_capture_end(pattern, text, group [, unit])
+/// regex_capture_end(captures, group [, unit])
+pub fn regex_capture_end(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    capture_position(context, values, Position::End)
+}
+
+/// regex_capture_span(pattern, text, group [, unit])
+/// regex_capture_span(captures, group [, unit])
+pub fn regex_capture_span(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    capture_position(context, values, Position::Span)
+}
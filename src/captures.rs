@@ -7,8 +7,9 @@ use sqlite_loadable::{
 };
 use sqlite_loadable::{prelude::*, Error};
 
-use std::{ffi::c_void, mem, os::raw::c_int};
+use std::{mem, os::raw::c_int};
 
+use crate::ext::{vtab_config, VTabConfig};
 use crate::utils::{result_regex_captures, value_regex};
 
 static CREATE_SQL: &str = "CREATE TABLE x(captures, pattern hidden, contents text hidden)";
@@ -37,13 +38,13 @@ impl<'vtab> VTab<'vtab> for RegexCapturesTable {
     type Cursor = RegexCapturesCursor<'vtab>;
 
     fn connect(
-        _db: *mut sqlite3,
+        db: *mut sqlite3,
         _aux: Option<&Self::Aux>,
         _args: VTabArguments,
     ) -> Result<(String, RegexCapturesTable)> {
         let base: sqlite3_vtab = unsafe { mem::zeroed() };
         let vtab = RegexCapturesTable { base };
-        // TODO db.config(VTabConfig::Innocuous)?;
+        vtab_config(db, VTabConfig::Innocuous)?;
         Ok((CREATE_SQL.to_owned(), vtab))
     }
     fn destroy(&self) -> Result<()> {
@@ -106,6 +107,8 @@ pub struct RegexCapturesCursor<'vtab> {
     base: sqlite3_vtab_cursor,
     r_clone: Option<Regex>,
     all_captures: Option<Vec<Captures<'vtab>>>,
+    /// The character offset of each match in all_captures.
+    char_starts: Vec<usize>,
     curr: usize,
 }
 impl RegexCapturesCursor<'_> {
@@ -115,6 +118,7 @@ impl RegexCapturesCursor<'_> {
             base,
             r_clone: None,
             all_captures: None,
+            char_starts: vec![],
             curr: 0,
         }
     }
@@ -140,9 +144,16 @@ impl VTabCursor for RegexCapturesCursor<'_> {
         )?;
 
         let mut res = vec![];
+        let mut char_starts = vec![];
+        let (mut last, mut chars) = (0, 0);
         for captures in r.captures_iter(contents) {
+            let start = captures.get(0).map_or(0, |m| m.start());
+            chars += contents[last..start].chars().count();
+            last = start;
+            char_starts.push(chars);
             res.push(captures)
         }
+        self.char_starts = char_starts;
         self.r_clone = Some((r).clone());
         self.all_captures = Some(res);
         self.curr = 0;
@@ -175,7 +186,12 @@ impl VTabCursor for RegexCapturesCursor<'_> {
             })?;
         match column(i) {
             Some(Columns::Captures) => {
-                result_regex_captures(context, self.r_clone.as_ref().unwrap(), captures);
+                result_regex_captures(
+                    context,
+                    self.r_clone.as_ref().unwrap(),
+                    captures,
+                    self.char_starts[self.curr],
+                );
             }
             Some(Columns::Pattern) => (),
             Some(Columns::Contents) => (),
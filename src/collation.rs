@@ -0,0 +1,214 @@
+//! regex_create_collation(), for collations that compare the typed capture
+//! groups of a pattern. sqlite-loadable's define_collation() can't carry
+//! per-collation state, so this calls sqlite3_create_collation_v2() directly.
+
+use regex::{Captures, Regex};
+use sqlite_loadable::prelude::*;
+use sqlite_loadable::{api, Error, Result};
+
+use std::{
+    cmp::Ordering,
+    ffi::CString,
+    os::raw::{c_int, c_void},
+    slice,
+};
+
+use crate::ext::{sqlite3_api, EXPECT_MESSAGE};
+use crate::utils::value_regex_owned;
+
+#[derive(Clone, Copy)]
+enum KeyType {
+    Text,
+    Int,
+    Real,
+}
+
+enum KeyGroup {
+    Index(usize),
+    Name(String),
+}
+
+struct SortKey {
+    group: KeyGroup,
+    key_type: KeyType,
+}
+
+struct RegexCollation {
+    regex: Regex,
+    keys: Vec<SortKey>,
+}
+
+/// Parses a spec like `'prefix:text,num:int'`. Groups can be capture group
+/// names or indexes.
+fn parse_spec(regex: &Regex, spec: &str) -> Result<Vec<SortKey>> {
+    let mut keys = vec![];
+    for entry in spec.split(',').map(str::trim) {
+        let (group, key_type) = entry.split_once(':').ok_or_else(|| {
+            Error::new_message(
+                format!(
+                    "expected collation spec entries like 'group:type', found '{}'",
+                    entry
+                )
+                .as_str(),
+            )
+        })?;
+        let key_type = match key_type.trim() {
+            "text" => KeyType::Text,
+            "int" => KeyType::Int,
+            "real" => KeyType::Real,
+            other => {
+                return Err(Error::new_message(
+                    format!(
+                        "unknown collation type '{}', expected one of 'text', 'int', 'real'",
+                        other
+                    )
+                    .as_str(),
+                ))
+            }
+        };
+        let group = group.trim();
+        let group = match group.parse::<usize>() {
+            Ok(index) if index < regex.captures_len() => KeyGroup::Index(index),
+            Ok(index) => {
+                return Err(Error::new_message(
+                    format!("no capture group at index {} in pattern", index).as_str(),
+                ))
+            }
+            Err(_) if regex.capture_names().flatten().any(|name| name == group) => {
+                KeyGroup::Name(group.to_owned())
+            }
+            Err(_) => {
+                return Err(Error::new_message(
+                    format!("no capture group named '{}' in pattern", group).as_str(),
+                ))
+            }
+        };
+        keys.push(SortKey { group, key_type });
+    }
+    Ok(keys)
+}
+
+/// Numbers sort before values that can't be parsed as one.
+fn compare_parsed<T: std::str::FromStr + PartialOrd>(a: &str, b: &str) -> Ordering {
+    match (a.parse::<T>(), b.parse::<T>()) {
+        (Ok(a), Ok(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+impl RegexCollation {
+    fn group<'t>(&self, captures: &Captures<'t>, group: &KeyGroup) -> Option<&'t str> {
+        match group {
+            KeyGroup::Index(index) => captures.get(*index),
+            KeyGroup::Name(name) => captures.name(name),
+        }
+        .map(|m| m.as_str())
+    }
+
+    /// Compares the typed groups of each key in order. Groups that didn't
+    /// participate in the match sort first, and strings that don't match the
+    /// pattern at all sort after the ones that do. Ties fall back to a binary
+    /// comparison.
+    fn compare(&self, a: &str, b: &str) -> Ordering {
+        let ordering = match (self.regex.captures(a), self.regex.captures(b)) {
+            (Some(a), Some(b)) => self
+                .keys
+                .iter()
+                .map(
+                    |key| match (self.group(&a, &key.group), self.group(&b, &key.group)) {
+                        (Some(a), Some(b)) => match key.key_type {
+                            KeyType::Text => a.cmp(b),
+                            KeyType::Int => compare_parsed::<i64>(a, b),
+                            KeyType::Real => compare_parsed::<f64>(a, b),
+                        },
+                        (None, Some(_)) => Ordering::Less,
+                        (Some(_), None) => Ordering::Greater,
+                        (None, None) => Ordering::Equal,
+                    },
+                )
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(Ordering::Equal),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+        ordering.then_with(|| a.cmp(b))
+    }
+}
+
+unsafe extern "C" fn x_compare(
+    p_arg: *mut c_void,
+    n1: c_int,
+    p1: *const c_void,
+    n2: c_int,
+    p2: *const c_void,
+) -> c_int {
+    let collation = &*(p_arg as *const RegexCollation);
+    let a = slice::from_raw_parts(p1 as *const u8, n1 as usize);
+    let b = slice::from_raw_parts(p2 as *const u8, n2 as usize);
+    let ordering = match (std::str::from_utf8(a), std::str::from_utf8(b)) {
+        (Ok(a), Ok(b)) => collation.compare(a, b),
+        _ => a.cmp(b),
+    };
+    ordering as c_int
+}
+
+unsafe extern "C" fn x_destroy(p_arg: *mut c_void) {
+    drop(Box::from_raw(p_arg as *mut RegexCollation));
+}
+
+/// regex_create_collation(name, pattern, spec)
+pub fn regex_create_collation(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    let name = api::value_text_notnull(
+        values
+            .first()
+            .ok_or_else(|| Error::new_message("expected 1st argument as name"))?,
+    )?;
+    let db = api::context_db_handle(context);
+    let regex = value_regex_owned(
+        values
+            .get(1)
+            .ok_or_else(|| Error::new_message("expected 2nd argument as pattern"))?,
+    )?;
+    let spec = api::value_text_notnull(
+        values
+            .get(2)
+            .ok_or_else(|| Error::new_message("expected 3rd argument as spec"))?,
+    )?;
+    let keys = parse_spec(&regex, spec)?;
+
+    let cname = CString::new(name)?;
+    let collation = Box::into_raw(Box::new(RegexCollation { regex, keys }));
+    let rc = unsafe {
+        (sqlite3_api().create_collation_v2.expect(EXPECT_MESSAGE))(
+            db,
+            cname.as_ptr(),
+            1, // SQLITE_UTF8
+            collation as *mut c_void,
+            Some(x_compare),
+            Some(x_destroy),
+        )
+    };
+    if rc != 0 {
+        // unlike other SQLite APIs, x_destroy isn't called when this fails
+        unsafe { x_destroy(collation as *mut c_void) };
+        let message = if rc == 5 {
+            // SQLITE_BUSY: SQLite can't replace a collation while statements
+            // are running, which always includes the one calling this function
+            format!(
+                "Error creating collation '{}': a collation with that name already exists",
+                name
+            )
+        } else {
+            format!("Error creating collation '{}', error code {}", name, rc)
+        };
+        return Err(Error::new_message(message.as_str()));
+    }
+    api::result_text(context, name)?;
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use regex::Regex;
+use sqlite_loadable::prelude::*;
+use sqlite_loadable::{api, Error, Result};
+
+use crate::utils::result_regex;
+
+/// Replaces every `{{name}}` placeholder in `template` with the matching
+/// sub-pattern, wrapped in a non-capturing group. `lookup` returns None for
+/// unknown names.
+pub(crate) fn compose_pattern<F>(template: &str, mut lookup: F) -> Result<String>
+where
+    F: FnMut(&str) -> Result<Option<String>>,
+{
+    let mut pattern = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let end = rest[start..].find("}}").ok_or_else(|| {
+            Error::new_message(format!("unclosed placeholder at '{}'", &rest[start..]).as_str())
+        })? + start;
+        let name = rest[start + 2..end].trim();
+        let sub = lookup(name)?.ok_or_else(|| {
+            Error::new_message(
+                format!("no sub-pattern provided for placeholder '{{{{{}}}}}'", name).as_str(),
+            )
+        })?;
+        pattern.push_str(&rest[..start]);
+        pattern.push_str("(?:");
+        pattern.push_str(&sub);
+        pattern.push(')');
+        rest = &rest[end + 2..];
+    }
+    pattern.push_str(rest);
+    Ok(pattern)
+}
+
+/// regex_compose(template, subpatterns)
+pub fn regex_compose(context: *mut sqlite3_context, values: &[*mut sqlite3_value]) -> Result<()> {
+    let template = api::value_text_notnull(
+        values
+            .first()
+            .ok_or_else(|| Error::new_message("expected 1st argument as template"))?,
+    )?;
+    let subpatterns = api::value_json(
+        values
+            .get(1)
+            .ok_or_else(|| Error::new_message("expected 2nd argument as subpatterns"))?,
+    )
+    .map_err(|err| {
+        Error::new_message(format!("Error parsing subpatterns as JSON: {}", err).as_str())
+    })?;
+    let subpatterns = subpatterns
+        .as_object()
+        .ok_or_else(|| Error::new_message("expected subpatterns to be a JSON object"))?;
+
+    let pattern = compose_pattern(template, |name| match subpatterns.get(name) {
+        None => Ok(None),
+        Some(value) => value
+            .as_str()
+            .map(|sub| Some(sub.to_owned()))
+            .ok_or_else(|| {
+                Error::new_message(
+                    format!("expected sub-pattern '{}' to be a string", name).as_str(),
+                )
+            }),
+    })?;
+
+    let regex = Regex::new(&pattern).map_err(|err| {
+        Error::new_message(format!("Error parsing composed pattern as regex: {}", err).as_str())
+    })?;
+    result_regex(context, regex);
+    Ok(())
+}
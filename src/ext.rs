@@ -0,0 +1,174 @@
+//! sqlite-loadable doesn't wrap every SQLite API this extension needs, so this
+//! keeps its own copy of the sqlite3_api_routines pointer (set by the
+//! entrypoint) to call those APIs directly.
+
+use sqlite_loadable::ext::sqlite3_stmt;
+use sqlite_loadable::prelude::*;
+use sqlite_loadable::{Error, Result};
+
+use std::{
+    ffi::{CStr, CString},
+    os::raw::c_int,
+    ptr, slice,
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
+/// Every entrypoint stores the pointer it's given, and connections can load
+/// the extension from different threads at once, so this is atomic.
+static SQLITE3_API: AtomicPtr<sqlite3_api_routines> = AtomicPtr::new(ptr::null_mut());
+
+pub(crate) static EXPECT_MESSAGE: &str =
+    "sqlite-regex internal error: expected method on SQLITE3_API. Please file an issue";
+
+/// MUST be called in the entrypoint, before any functions or modules are defined.
+pub(crate) fn init_api(p_api: *mut sqlite3_api_routines) {
+    SQLITE3_API.store(p_api, Ordering::Release);
+}
+
+/// The sqlite3_api_routines stored by init_api().
+///
+/// # Safety
+///
+/// init_api() must have been called first.
+pub(crate) unsafe fn sqlite3_api() -> &'static sqlite3_api_routines {
+    &*SQLITE3_API.load(Ordering::Acquire)
+}
+
+/// Options for a virtual table, passed to sqlite3_vtab_config() in `connect`.
+pub(crate) enum VTabConfig {
+    /// The table has no side effects, so it can be used in schemas and
+    /// triggers even with `trusted_schema=OFF`.
+    Innocuous,
+    /// The table can only be used in top-level SQL, never in schemas or
+    /// triggers.
+    DirectOnly,
+}
+
+/// SQLite only hands its sqlite3_api_routines to extension entrypoints, so
+/// this briefly registers an auto extension that records it, and opens a
+/// throwaway connection to run it.
+#[cfg(feature = "rusqlite")]
+pub(crate) fn rusqlite_api() -> rusqlite::Result<*mut sqlite3_api_routines> {
+    use rusqlite::ffi;
+    use std::os::raw::c_char;
+
+    static CAPTURED_API: AtomicPtr<sqlite3_api_routines> = AtomicPtr::new(ptr::null_mut());
+
+    unsafe extern "C" fn capture_api(
+        _db: *mut sqlite3,
+        _pz_err_msg: *mut *mut c_char,
+        p_api: *mut sqlite3_api_routines,
+    ) -> c_int {
+        CAPTURED_API.store(p_api, Ordering::SeqCst);
+        0
+    }
+
+    let api = CAPTURED_API.load(Ordering::SeqCst);
+    if !api.is_null() {
+        return Ok(api);
+    }
+    let entrypoint = unsafe {
+        std::mem::transmute::<
+            unsafe extern "C" fn(
+                *mut sqlite3,
+                *mut *mut c_char,
+                *mut sqlite3_api_routines,
+            ) -> c_int,
+            unsafe extern "C" fn(),
+        >(capture_api)
+    };
+    unsafe { ffi::sqlite3_auto_extension(Some(entrypoint)) };
+    let connection = rusqlite::Connection::open_in_memory();
+    unsafe { ffi::sqlite3_cancel_auto_extension(Some(entrypoint)) };
+    connection?;
+
+    let api = CAPTURED_API.load(Ordering::SeqCst);
+    if api.is_null() {
+        return Err(rusqlite::Error::SqliteFailure(
+            ffi::Error::new(ffi::SQLITE_ERROR),
+            Some("could not get the sqlite3_api_routines from SQLite".to_owned()),
+        ));
+    }
+    Ok(api)
+}
+
+/// The message of the most recent error on `db`.
+pub(crate) unsafe fn errmsg(db: *mut sqlite3) -> String {
+    let message = (sqlite3_api().errmsg.expect(EXPECT_MESSAGE))(db);
+    if message.is_null() {
+        return "unknown error".to_owned();
+    }
+    CStr::from_ptr(message).to_string_lossy().into_owned()
+}
+
+/// Runs `sql` with `params` bound as text, and returns the columns of its
+/// first row as text, or None if it didn't return any rows. Unlike
+/// sqlite_loadable's exec::Statement, this surfaces SQLite's error message
+/// when preparing or stepping fails.
+pub(crate) fn query_row(
+    db: *mut sqlite3,
+    sql: &str,
+    params: &[&str],
+) -> Result<Option<Vec<String>>> {
+    let sql = CString::new(sql)?;
+    let mut stmt: *mut sqlite3_stmt = ptr::null_mut();
+    unsafe {
+        let rc = (sqlite3_api().prepare_v2.expect(EXPECT_MESSAGE))(
+            db,
+            sql.as_ptr(),
+            -1,
+            &mut stmt,
+            ptr::null_mut(),
+        );
+        if rc != 0 {
+            return Err(Error::new_message(errmsg(db).as_str()));
+        }
+        for (i, param) in params.iter().enumerate() {
+            // SQLITE_STATIC is fine, since `params` outlives the statement
+            (sqlite3_api().bind_text.expect(EXPECT_MESSAGE))(
+                stmt,
+                i as c_int + 1,
+                param.as_ptr().cast(),
+                param.len() as c_int,
+                None,
+            );
+        }
+        let result = match (sqlite3_api().step.expect(EXPECT_MESSAGE))(stmt) {
+            100 => {
+                // SQLITE_ROW
+                let n = (sqlite3_api().column_count.expect(EXPECT_MESSAGE))(stmt);
+                let columns = (0..n)
+                    .map(|i| {
+                        let text = (sqlite3_api().column_text.expect(EXPECT_MESSAGE))(stmt, i);
+                        let len = (sqlite3_api().column_bytes.expect(EXPECT_MESSAGE))(stmt, i);
+                        if text.is_null() {
+                            return String::new();
+                        }
+                        String::from_utf8_lossy(slice::from_raw_parts(text, len as usize))
+                            .into_owned()
+                    })
+                    .collect();
+                Ok(Some(columns))
+            }
+            // SQLITE_DONE
+            101 => Ok(None),
+            _ => Err(Error::new_message(errmsg(db).as_str())),
+        };
+        (sqlite3_api().finalize.expect(EXPECT_MESSAGE))(stmt);
+        result
+    }
+}
+
+pub(crate) fn vtab_config(db: *mut sqlite3, config: VTabConfig) -> Result<()> {
+    let op: c_int = match config {
+        VTabConfig::Innocuous => 2,  // SQLITE_VTAB_INNOCUOUS
+        VTabConfig::DirectOnly => 3, // SQLITE_VTAB_DIRECTONLY
+    };
+    let rc = unsafe { (sqlite3_api().vtab_config.expect(EXPECT_MESSAGE))(db, op) };
+    if rc != 0 {
+        return Err(Error::new_message(
+            format!("could not configure virtual table, error code {rc}").as_str(),
+        ));
+    }
+    Ok(())
+}
@@ -7,7 +7,10 @@ use sqlite_loadable::{prelude::*, Error};
 
 use std::{marker::PhantomData, mem, os::raw::c_int};
 
-use crate::utils::value_regex;
+use crate::{
+    ext::{vtab_config, VTabConfig},
+    utils::value_regex,
+};
 
 static CREATE_SQL: &str =
     "CREATE TABLE x(start int, end int, match text, pattern hidden, contents text hidden)";
@@ -40,13 +43,13 @@ impl<'vtab> VTab<'vtab> for RegexFindAllTable {
     type Cursor = RegexFindAllCursor<'vtab>;
 
     fn connect(
-        _db: *mut sqlite3,
+        db: *mut sqlite3,
         _aux: Option<&Self::Aux>,
         _args: VTabArguments,
     ) -> Result<(String, RegexFindAllTable)> {
         let base: sqlite3_vtab = unsafe { mem::zeroed() };
         let vtab = RegexFindAllTable { base };
-        // TODO db.config(VTabConfig::Innocuous)?;
+        vtab_config(db, VTabConfig::Innocuous)?;
         Ok((CREATE_SQL.to_owned(), vtab))
     }
     fn destroy(&self) -> Result<()> {
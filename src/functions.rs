@@ -0,0 +1,173 @@
+//! Safe Rust versions of the core SQL functions, so Rust code matches text the
+//! same way SQL does. The SQL functions call into these.
+//!
+//! Named patterns from `regex_define()` live in the database, so they're only
+//! available in SQL, through `regex_named()`.
+
+use regex::{Captures, Match, Regex};
+use regex_syntax::ast;
+use std::{borrow::Cow, collections::VecDeque};
+
+/// A capture group, either by index (0 is the entire match) or by name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Group<'a> {
+    Index(usize),
+    Name(&'a str),
+}
+
+/// Flags that `regex(pattern, flags)` accepts: the inline flags `imsxUu`,
+/// plus `A`, which anchors the pattern instead.
+const FLAGS: &str = "imsxUuA";
+
+/// Same as `regex(pattern, flags)`. `flags` are inline flags like `"i"` or
+/// `"ms"`, and can be empty. `"A"` anchors the pattern so it only matches the
+/// entire text, see [`anchored_pattern`].
+///
+/// ```
+/// use sqlite_regex::functions::regex;
+///
+/// assert!(regex("abc", "i")?.is_match("xABCx"));
+///
+/// let anchored = regex("a|ab", "A")?;
+/// assert_eq!(anchored.as_str(), r"\A(?:a|ab)\z");
+/// assert!(anchored.is_match("ab"));
+/// assert!(!anchored.is_match("abc"));
+/// assert!(regex("ABC", "iA")?.is_match("abc"));
+///
+/// let err = regex("abc", "q").unwrap_err();
+/// assert!(err.to_string().contains("unknown regex flag 'q'"));
+/// # Ok::<(), regex::Error>(())
+/// ```
+pub fn regex(pattern: &str, flags: &str) -> Result<Regex, regex::Error> {
+    if let Some(flag) = flags.chars().find(|c| !FLAGS.contains(*c)) {
+        return Err(regex::Error::Syntax(format!(
+            "unknown regex flag '{}', expected one of '{}'",
+            flag, FLAGS
+        )));
+    }
+    let inline = flags.replace('A', "");
+    let pattern = if inline.is_empty() {
+        pattern.to_owned()
+    } else {
+        format!("(?{}){}", inline, pattern)
+    };
+    if flags.contains('A') {
+        Regex::new(&anchored_pattern(&pattern))
+    } else {
+        Regex::new(&pattern)
+    }
+}
+
+/// Wraps `pattern` as `\A(?:pattern)\z`, so it only matches the entire text.
+/// The pattern is printed back from its syntax tree first, since a trailing
+/// `#` comment in `(?x)` mode would otherwise swallow the closing `)`.
+///
+/// Patterns that don't parse are returned as-is, so compiling them reports the
+/// same error as without anchoring.
+pub fn anchored_pattern(pattern: &str) -> String {
+    let ast = match ast::parse::Parser::new().parse(pattern) {
+        Ok(ast) => ast,
+        Err(_) => return pattern.to_owned(),
+    };
+    let mut printed = String::new();
+    if ast::print::Printer::new()
+        .print(&ast, &mut printed)
+        .is_err()
+    {
+        return pattern.to_owned();
+    }
+    format!(r"\A(?:{})\z", printed)
+}
+
+/// Same as `regexp(pattern, text)`.
+pub fn regexp(regex: &Regex, text: &str) -> bool {
+    regex.is_match(text)
+}
+
+/// Same as `regex_valid(pattern)`.
+pub fn regex_valid(pattern: &str) -> bool {
+    Regex::new(pattern).is_ok()
+}
+
+/// Same as `regex_find(pattern, text)`.
+pub fn regex_find<'t>(regex: &Regex, text: &'t str) -> Option<&'t str> {
+    regex.find(text).map(|m| m.as_str())
+}
+
+/// Same as `regex_find_nth(pattern, text, n)`. `n` is 1-based, and negative
+/// `n` counts from the last match, so `-1` is the last one. `0` is never a match.
+pub fn regex_find_nth<'t>(regex: &Regex, text: &'t str, n: i64) -> Option<&'t str> {
+    nth(regex.find_iter(text), n).map(|m| m.as_str())
+}
+
+/// Same as `regex_replace(pattern, text, replacement)`.
+pub fn regex_replace<'t>(regex: &Regex, text: &'t str, replacement: &str) -> Cow<'t, str> {
+    regex.replace(text, replacement)
+}
+
+/// Same as `regex_replace_all(pattern, text, replacement)`.
+pub fn regex_replace_all<'t>(regex: &Regex, text: &'t str, replacement: &str) -> Cow<'t, str> {
+    regex.replace_all(text, replacement)
+}
+
+/// Same as `regex_capture(pattern, text, group)`.
+pub fn regex_capture<'t>(regex: &Regex, text: &'t str, group: Group) -> Option<&'t str> {
+    regex
+        .captures(text)
+        .and_then(|captures| capture_group(&captures, group))
+}
+
+/// Same as `regex_capture_nth(pattern, text, n, group)`, with `n` like in
+/// [`regex_find_nth`].
+pub fn regex_capture_nth<'t>(
+    regex: &Regex,
+    text: &'t str,
+    n: i64,
+    group: Group,
+) -> Option<&'t str> {
+    nth(regex.captures_iter(text), n).and_then(|captures| capture_group(&captures, group))
+}
+
+/// Same as `regex_extract_all(pattern, text, group)`, with `None` for matches
+/// where `group` didn't participate.
+pub fn regex_extract_all<'t>(regex: &Regex, text: &'t str, group: Group) -> Vec<Option<&'t str>> {
+    regex
+        .captures_iter(text)
+        .map(|captures| capture_group(&captures, group))
+        .collect()
+}
+
+pub(crate) fn capture_group<'t>(captures: &Captures<'t>, group: Group) -> Option<&'t str> {
+    capture_match(captures, group).map(|m| m.as_str())
+}
+
+pub(crate) fn capture_match<'t>(captures: &Captures<'t>, group: Group) -> Option<Match<'t>> {
+    match group {
+        Group::Index(index) => captures.get(index),
+        Group::Name(name) => captures.name(name),
+    }
+}
+
+/// The 1-based `n`th item, or the `-n`th item from the end when negative. Only
+/// the last `-n` items are kept while counting from the end.
+fn nth<I: Iterator>(mut iter: I, n: i64) -> Option<I::Item> {
+    match n {
+        0 => None,
+        n if n > 0 => iter.nth(usize::try_from(n - 1).ok()?),
+        n => {
+            let keep = usize::try_from(n.unsigned_abs()).ok()?;
+            let mut last = VecDeque::with_capacity(keep.min(64));
+            for item in iter {
+                if last.len() == keep {
+                    last.pop_front();
+                }
+                last.push_back(item);
+            }
+            if last.len() == keep {
+                last.pop_front()
+            } else {
+                None
+            }
+        }
+    }
+}
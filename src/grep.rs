@@ -0,0 +1,354 @@
+use regex::Regex;
+use sqlite_loadable::{
+    api,
+    table::{ConstraintOperator, IndexInfo, VTab, VTabArguments, VTabCursor},
+    BestIndexError, Result,
+};
+use sqlite_loadable::{prelude::*, Error};
+
+use std::{
+    fs::{self, File},
+    io::{BufReader, Read, Seek},
+    mem,
+    os::raw::c_int,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    ext::{vtab_config, VTabConfig},
+    read_lines::read_line,
+    utils::{glob_to_pattern, value_regex_owned},
+};
+
+static CREATE_SQL: &str = "CREATE TABLE x(path text, line_number int, line text, start int, end int, match text, pattern hidden, root_dir hidden, glob hidden)";
+enum Columns {
+    Path,
+    LineNumber,
+    Line,
+    Start,
+    End,
+    Match,
+    Pattern,
+    RootDir,
+    Glob,
+}
+fn column(index: i32) -> Option<Columns> {
+    match index {
+        0 => Some(Columns::Path),
+        1 => Some(Columns::LineNumber),
+        2 => Some(Columns::Line),
+        3 => Some(Columns::Start),
+        4 => Some(Columns::End),
+        5 => Some(Columns::Match),
+        6 => Some(Columns::Pattern),
+        7 => Some(Columns::RootDir),
+        8 => Some(Columns::Glob),
+        _ => None,
+    }
+}
+
+/// Number of leading bytes inspected when deciding if a file is binary,
+/// the same heuristic git and grep use.
+const BINARY_SNIFF_LEN: usize = 8000;
+
+/// Returns true if the file starts with a NUL byte in its first few kilobytes.
+/// The file is rewound to the start afterwards.
+fn is_binary(file: &mut File) -> std::io::Result<bool> {
+    let mut buf = vec![0; BINARY_SNIFF_LEN];
+    let n = file.read(&mut buf)?;
+    file.rewind()?;
+    Ok(buf[..n].contains(&0))
+}
+
+#[repr(C)]
+pub struct RegexGrepTable {
+    /// must be first
+    base: sqlite3_vtab,
+}
+
+impl<'vtab> VTab<'vtab> for RegexGrepTable {
+    type Aux = ();
+    type Cursor = RegexGrepCursor;
+
+    fn connect(
+        db: *mut sqlite3,
+        _aux: Option<&Self::Aux>,
+        _args: VTabArguments,
+    ) -> Result<(String, RegexGrepTable)> {
+        let base: sqlite3_vtab = unsafe { mem::zeroed() };
+        let vtab = RegexGrepTable { base };
+        vtab_config(db, VTabConfig::DirectOnly)?;
+        Ok((CREATE_SQL.to_owned(), vtab))
+    }
+    fn destroy(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn best_index(&self, mut info: IndexInfo) -> core::result::Result<(), BestIndexError> {
+        let mut has_pattern = false;
+        let mut has_root_dir = false;
+        let mut has_glob = false;
+        for mut constraint in info.constraints() {
+            match column(constraint.column_idx()) {
+                Some(Columns::Pattern) => {
+                    if constraint.usable() && constraint.op() == Some(ConstraintOperator::EQ) {
+                        constraint.set_omit(true);
+                        constraint.set_argv_index(1);
+                        has_pattern = true;
+                    } else {
+                        return Err(BestIndexError::Constraint);
+                    }
+                }
+                Some(Columns::RootDir) => {
+                    if constraint.usable() && constraint.op() == Some(ConstraintOperator::EQ) {
+                        constraint.set_omit(true);
+                        constraint.set_argv_index(2);
+                        has_root_dir = true;
+                    } else {
+                        return Err(BestIndexError::Constraint);
+                    }
+                }
+                Some(Columns::Glob) => {
+                    if constraint.usable() && constraint.op() == Some(ConstraintOperator::EQ) {
+                        constraint.set_omit(true);
+                        constraint.set_argv_index(3);
+                        has_glob = true;
+                    } else {
+                        return Err(BestIndexError::Constraint);
+                    }
+                }
+                _ => (),
+            }
+        }
+        if !has_pattern || !has_root_dir {
+            return Err(BestIndexError::Error);
+        }
+        info.set_estimated_cost(1000000.0);
+        info.set_estimated_rows(100000);
+        info.set_idxnum(if has_glob { 3 } else { 2 });
+
+        Ok(())
+    }
+
+    fn open(&mut self) -> Result<RegexGrepCursor> {
+        Ok(RegexGrepCursor::new())
+    }
+}
+
+#[repr(C)]
+pub struct RegexGrepCursor {
+    /// Base class. Must be first
+    base: sqlite3_vtab_cursor,
+    regex: Option<Regex>,
+    glob: Option<Regex>,
+    root: PathBuf,
+    /// Files and directories not yet visited, in reverse order.
+    pending: Vec<PathBuf>,
+    reader: Option<BufReader<File>>,
+    path: String,
+    line_number: i64,
+    line: String,
+    matches: Vec<(usize, usize)>,
+    curr: usize,
+    rowid: i64,
+    eof: bool,
+}
+impl RegexGrepCursor {
+    fn new() -> RegexGrepCursor {
+        let base: sqlite3_vtab_cursor = unsafe { mem::zeroed() };
+        RegexGrepCursor {
+            base,
+            regex: None,
+            glob: None,
+            root: PathBuf::new(),
+            pending: vec![],
+            reader: None,
+            path: String::new(),
+            line_number: 0,
+            line: String::new(),
+            matches: vec![],
+            curr: 0,
+            rowid: 0,
+            eof: true,
+        }
+    }
+
+    /// Opens the next non-binary file that matches the glob, walking into
+    /// directories depth-first in sorted order. Symlinks below the root are not
+    /// followed, and unreadable entries are skipped. Returns false once every
+    /// file is visited.
+    fn open_next_file(&mut self) -> Result<bool> {
+        while let Some(path) = self.pending.pop() {
+            let metadata = if path == self.root {
+                fs::metadata(&path)
+            } else {
+                fs::symlink_metadata(&path)
+            };
+            let file_type = match metadata {
+                Ok(metadata) => metadata.file_type(),
+                Err(_) => continue,
+            };
+            if file_type.is_dir() {
+                let mut children: Vec<PathBuf> = match fs::read_dir(&path) {
+                    Ok(entries) => entries.flatten().map(|entry| entry.path()).collect(),
+                    Err(_) => continue,
+                };
+                children.sort();
+                self.pending.extend(children.into_iter().rev());
+                continue;
+            }
+            if !file_type.is_file() || !self.glob_matches(&path) {
+                continue;
+            }
+            let mut file = match File::open(&path) {
+                Ok(file) => file,
+                Err(_) => continue,
+            };
+            if is_binary(&mut file).unwrap_or(true) {
+                continue;
+            }
+            self.reader = Some(BufReader::new(file));
+            self.path = path.to_string_lossy().into_owned();
+            self.line_number = 0;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// The glob is matched against the path relative to the root directory.
+    fn glob_matches(&self, path: &Path) -> bool {
+        let glob = match &self.glob {
+            Some(glob) => glob,
+            None => return true,
+        };
+        match path.strip_prefix(&self.root) {
+            Ok(relative) if relative.as_os_str().is_empty() => true,
+            Ok(relative) => glob.is_match(&relative.to_string_lossy()),
+            Err(_) => glob.is_match(&path.to_string_lossy()),
+        }
+    }
+
+    /// Reads the next line across all files. Returns false when there are no
+    /// more lines in any file.
+    fn next_line(&mut self) -> Result<bool> {
+        loop {
+            if let Some(reader) = self.reader.as_mut() {
+                if read_line(reader, &mut self.line)? {
+                    self.line_number += 1;
+                    let regex = self.regex.as_ref().ok_or_else(|| {
+                        Error::new_message("sqlite-regex internal error: self.regex is not defined")
+                    })?;
+                    self.matches = regex
+                        .find_iter(&self.line)
+                        .map(|m| (m.start(), m.end()))
+                        .collect();
+                    return Ok(true);
+                }
+                self.reader = None;
+            }
+            if !self.open_next_file()? {
+                return Ok(false);
+            }
+        }
+    }
+
+    /// Moves forward until `curr` points at a match, or all files are exhausted.
+    fn settle(&mut self) -> Result<()> {
+        while self.curr >= self.matches.len() {
+            if !self.next_line()? {
+                self.eof = true;
+                return Ok(());
+            }
+            self.curr = 0;
+        }
+        Ok(())
+    }
+}
+
+impl VTabCursor for RegexGrepCursor {
+    fn filter(
+        &mut self,
+        idx_num: c_int,
+        _idx_str: Option<&str>,
+        values: &[*mut sqlite3_value],
+    ) -> Result<()> {
+        let regex = value_regex_owned(
+            values
+                .first()
+                .ok_or_else(|| Error::new_message("expected 1st argument as regex"))?,
+        )?;
+        let root_dir = api::value_text_notnull(
+            values
+                .get(1)
+                .ok_or_else(|| Error::new_message("expected 2nd argument as root_dir"))?,
+        )?;
+        self.glob = if idx_num == 3 {
+            let glob = api::value_text_notnull(
+                values
+                    .get(2)
+                    .ok_or_else(|| Error::new_message("expected 3rd argument as glob"))?,
+            )?;
+            Some(Regex::new(&glob_to_pattern(glob)).map_err(|err| {
+                Error::new_message(format!("Error parsing glob: {}", err).as_str())
+            })?)
+        } else {
+            None
+        };
+
+        fs::metadata(root_dir).map_err(|err| {
+            Error::new_message(format!("Error reading {}: {}", root_dir, err).as_str())
+        })?;
+        self.regex = Some(regex);
+        self.root = PathBuf::from(root_dir);
+        self.pending = vec![self.root.clone()];
+        self.reader = None;
+        self.matches = vec![];
+        self.curr = 0;
+        self.rowid = 0;
+        self.eof = false;
+        self.settle()
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.curr += 1;
+        self.rowid += 1;
+        self.settle()
+    }
+
+    fn eof(&self) -> bool {
+        self.eof
+    }
+
+    fn column(&self, context: *mut sqlite3_context, i: c_int) -> Result<()> {
+        let (start, end) = self.matches.get(self.curr).ok_or_else(|| {
+            Error::new_message("sqlite-regex internal error: self.curr greater than matches result")
+        })?;
+        match column(i) {
+            Some(Columns::Path) => {
+                api::result_text(context, &self.path)?;
+            }
+            Some(Columns::LineNumber) => {
+                api::result_int64(context, self.line_number);
+            }
+            Some(Columns::Line) => {
+                api::result_text(context, &self.line)?;
+            }
+            Some(Columns::Start) => {
+                api::result_int64(context, *start as i64);
+            }
+            Some(Columns::End) => {
+                api::result_int64(context, *end as i64);
+            }
+            Some(Columns::Match) => {
+                api::result_text(context, &self.line[*start..*end])?;
+            }
+            Some(Columns::Pattern) | Some(Columns::RootDir) | Some(Columns::Glob) => (),
+            None => (),
+        }
+        Ok(())
+    }
+
+    fn rowid(&self) -> Result<i64> {
+        Ok(self.rowid)
+    }
+}
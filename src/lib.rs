@@ -1,10 +1,30 @@
+mod acset;
+mod acset_find_all;
+mod aggregate;
+mod builtins;
+mod capture_names;
+mod capture_span;
 mod captures;
+mod collation;
+mod compose;
+mod ext;
 mod find_all;
+pub mod functions;
+mod grep;
+mod literals;
 mod meta;
+mod named;
+mod read_lines;
+mod redact;
 mod regex;
+mod regex_agg;
 mod regexset;
 mod regexset_matches;
+mod replace_fn;
+mod replace_map;
+mod replacement;
 mod split;
+mod translate;
 mod utils;
 
 use regexset_matches::RegexSetMatchesTable;
@@ -15,41 +35,280 @@ use sqlite_loadable::{
 };
 
 use crate::{
-    captures::RegexCapturesTable, find_all::RegexFindAllTable, meta::*, regex::*, regexset::*,
+    acset::*,
+    acset_find_all::AcFindAllTable,
+    aggregate::{define_aggregate_function, define_window_function},
+    builtins::*,
+    capture_names::*,
+    capture_span::*,
+    captures::RegexCapturesTable,
+    collation::regex_create_collation,
+    compose::*,
+    find_all::RegexFindAllTable,
+    grep::RegexGrepTable,
+    literals::*,
+    meta::*,
+    named::*,
+    read_lines::RegexReadLinesTable,
+    redact::regex_redact,
+    regex::*,
+    regex_agg::*,
+    regexset::*,
+    replace_fn::regex_replace_all_fn,
+    replace_map::{regex_replace_map, RegexMapAgg},
     split::RegexSplitTable,
+    translate::*,
 };
 
-#[sqlite_entrypoint]
-pub fn sqlite3_regex_init(db: *mut sqlite3) -> Result<()> {
-    let flags = FunctionFlags::UTF8 | FunctionFlags::DETERMINISTIC;
+/// Calls `callback` as an extension entrypoint.
+unsafe fn entrypoint(
+    db: *mut sqlite3,
+    pz_err_msg: *mut *mut c_char,
+    p_api: *mut sqlite3_api_routines,
+    callback: fn(*mut sqlite3) -> Result<()>,
+) -> c_uint {
+    // aggregate functions and virtual tables call into SQLite directly, so they
+    // need the API routines before anything is defined
+    ext::init_api(p_api);
+    register_entrypoint(db, pz_err_msg, p_api, callback)
+}
+
+/// Registers every function and table function.
+///
+/// # Safety
+///
+/// Should only be called by underlying SQLite C APIs,
+/// like sqlite3_auto_extension and sqlite3_cancel_auto_extension.
+#[no_mangle]
+pub unsafe extern "C" fn sqlite3_regex_init(
+    db: *mut sqlite3,
+    pz_err_msg: *mut *mut c_char,
+    p_api: *mut sqlite3_api_routines,
+) -> c_uint {
+    entrypoint(db, pz_err_msg, p_api, regex_init)
+}
+
+/// Registers only pure scalar, aggregate and window functions, leaving out the
+/// ones that read or write the database, create collations or call other SQL
+/// functions.
+///
+/// # Safety
+///
+/// Should only be called by underlying SQLite C APIs,
+/// like sqlite3_auto_extension and sqlite3_cancel_auto_extension.
+#[no_mangle]
+pub unsafe extern "C" fn sqlite3_regex_scalar_init(
+    db: *mut sqlite3,
+    pz_err_msg: *mut *mut c_char,
+    p_api: *mut sqlite3_api_routines,
+) -> c_uint {
+    entrypoint(db, pz_err_msg, p_api, regex_scalar_init)
+}
+
+/// Registers only table functions, including the ones that read files.
+///
+/// # Safety
+///
+/// Should only be called by underlying SQLite C APIs,
+/// like sqlite3_auto_extension and sqlite3_cancel_auto_extension.
+#[no_mangle]
+pub unsafe extern "C" fn sqlite3_regex_tables_init(
+    db: *mut sqlite3,
+    pz_err_msg: *mut *mut c_char,
+    p_api: *mut sqlite3_api_routines,
+) -> c_uint {
+    entrypoint(db, pz_err_msg, p_api, regex_tables_init)
+}
+
+/// Registers pure functions and the table functions that don't touch the
+/// filesystem.
+///
+/// # Safety
+///
+/// Should only be called by underlying SQLite C APIs,
+/// like sqlite3_auto_extension and sqlite3_cancel_auto_extension.
+#[no_mangle]
+pub unsafe extern "C" fn sqlite3_regex_safe_init(
+    db: *mut sqlite3,
+    pz_err_msg: *mut *mut c_char,
+    p_api: *mut sqlite3_api_routines,
+) -> c_uint {
+    entrypoint(db, pz_err_msg, p_api, regex_safe_init)
+}
+
+/// Registers every sqlite-regex function and table function on a
+/// [`rusqlite::Connection`], without loading the extension at runtime.
+///
+/// ```
+/// let connection = rusqlite::Connection::open_in_memory()?;
+/// sqlite_regex::register(&connection)?;
+/// let domain: String = connection.query_row(
+///     "select regex_capture('@(.+)$', 'alex@example.com', 1)",
+///     [],
+///     |row| row.get(0),
+/// )?;
+/// assert_eq!(domain, "example.com");
+/// # Ok::<(), rusqlite::Error>(())
+/// ```
+#[cfg(feature = "rusqlite")]
+pub fn register(connection: &rusqlite::Connection) -> rusqlite::Result<()> {
+    let p_api = ext::rusqlite_api()?;
+    unsafe { sqlite_loadable::ext::faux_sqlite_extension_init2(p_api) };
+    ext::init_api(p_api);
+    regex_init(unsafe { connection.handle() }.cast()).map_err(|err| {
+        rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_ERROR),
+            Some(err.result_error_message()),
+        )
+    })
+}
+
+fn regex_init(db: *mut sqlite3) -> Result<()> {
+    regex_scalar_init(db)?;
+    regex_connection_init(db)?;
+    regex_tables_init(db)
+}
+
+fn regex_safe_init(db: *mut sqlite3) -> Result<()> {
+    regex_scalar_init(db)?;
+    regex_safe_tables_init(db)
+}
+
+fn regex_tables_init(db: *mut sqlite3) -> Result<()> {
+    regex_safe_tables_init(db)?;
+    regex_filesystem_tables_init(db)
+}
 
-    define_scalar_function(db, "regex_version", 0, regex_version, flags)?;
-    define_scalar_function(db, "regex_debug", 0, regex_debug, flags)?;
+fn regex_scalar_init(db: *mut sqlite3) -> Result<()> {
+    let innocuous = FunctionFlags::UTF8 | FunctionFlags::DETERMINISTIC | FunctionFlags::INNOCUOUS;
 
-    define_scalar_function(db, "regex", 1, regex, flags)?;
-    define_scalar_function(db, "regex_print", 1, regex_print, flags)?;
+    define_scalar_function(db, "regex_version", 0, regex_version, innocuous)?;
+    // exposes build details, so it's kept out of untrusted schemas
+    define_scalar_function(
+        db,
+        "regex_debug",
+        0,
+        regex_debug,
+        FunctionFlags::UTF8 | FunctionFlags::DETERMINISTIC,
+    )?;
 
-    define_scalar_function(db, "regexp", 2, regexp, flags)?;
+    define_scalar_function(db, "regex", 1, regex, innocuous)?;
+    define_scalar_function(db, "regex", 2, regex, innocuous)?;
+    define_scalar_function(db, "regex_print", 1, regex_print, innocuous)?;
+    define_scalar_function(db, "regex_ast", 1, regex_ast, innocuous)?;
+    define_scalar_function(db, "regex_hir", 1, regex_hir, innocuous)?;
+    define_scalar_function(db, "regex_literals", 1, regex_literals, innocuous)?;
+    define_scalar_function(db, "regex_from_like", 1, regex_from_like, innocuous)?;
+    define_scalar_function(db, "regex_from_like", 2, regex_from_like, innocuous)?;
+    define_scalar_function(db, "regex_from_glob", 1, regex_from_glob, innocuous)?;
+    define_scalar_function(db, "regex_compose", 2, regex_compose, innocuous)?;
 
-    define_scalar_function(db, "regex_valid", 1, regex_valid, flags)?;
+    define_scalar_function(db, "regexp", 2, regexp, innocuous)?;
 
-    define_scalar_function(db, "regex_find", 2, regex_find, flags)?;
-    define_scalar_function(db, "regex_find_at", 3, regex_find_at, flags)?;
+    define_scalar_function(db, "regex_valid", 1, regex_valid, innocuous)?;
+    define_scalar_function(db, "regex_full_match", 2, regex_full_match, innocuous)?;
 
-    define_scalar_function(db, "regex_replace", 3, regex_replace, flags)?;
-    define_scalar_function(db, "regex_replace_all", 3, regex_replace_all, flags)?;
+    define_scalar_function(db, "regex_find", 2, regex_find, innocuous)?;
+    define_scalar_function(db, "regex_find_at", 3, regex_find_at, innocuous)?;
+    define_scalar_function(db, "regex_find_nth", 3, regex_find_nth, innocuous)?;
 
-    define_scalar_function(db, "regex_capture", 3, regex_capture, flags)?;
-    define_scalar_function(db, "regex_capture", 2, regex_capture2, flags)?;
+    define_scalar_function(db, "regex_replace", 3, regex_replace, innocuous)?;
+    define_scalar_function(db, "regex_replace", 4, regex_replace, innocuous)?;
+    define_scalar_function(db, "regex_replace_all", 3, regex_replace_all, innocuous)?;
+    define_scalar_function(db, "regex_replace_all", 4, regex_replace_all, innocuous)?;
+    define_scalar_function(db, "regex_replace_map", 2, regex_replace_map, innocuous)?;
+    define_scalar_function(db, "regex_replace_map", 3, regex_replace_map, innocuous)?;
+    define_aggregate_function::<RegexMapAgg>(db, "regex_map_agg", 2, innocuous)?;
 
+    define_scalar_function(db, "regex_capture", 3, regex_capture, innocuous)?;
+    define_scalar_function(db, "regex_capture", 2, regex_capture2, innocuous)?;
+    define_scalar_function(db, "regex_capture_nth", 4, regex_capture_nth, innocuous)?;
+    define_scalar_function(db, "regex_capture_start", 2, regex_capture_start, innocuous)?;
+    define_scalar_function(db, "regex_capture_start", 3, regex_capture_start, innocuous)?;
+    define_scalar_function(db, "regex_capture_start", 4, regex_capture_start, innocuous)?;
+    define_scalar_function(db, "regex_capture_end", 2, regex_capture_end, innocuous)?;
+    define_scalar_function(db, "regex_capture_end", 3, regex_capture_end, innocuous)?;
+    define_scalar_function(db, "regex_capture_end", 4, regex_capture_end, innocuous)?;
+    define_scalar_function(db, "regex_capture_span", 2, regex_capture_span, innocuous)?;
+    define_scalar_function(db, "regex_capture_span", 3, regex_capture_span, innocuous)?;
+    define_scalar_function(db, "regex_capture_span", 4, regex_capture_span, innocuous)?;
+    define_scalar_function(db, "regex_extract_all", 2, regex_extract_all, innocuous)?;
+    define_scalar_function(db, "regex_extract_all", 3, regex_extract_all, innocuous)?;
+    define_aggregate_function::<RegexMatchesAgg>(db, "regex_matches_agg", 2, innocuous)?;
+    define_aggregate_function::<RegexCountAgg>(db, "regex_count_agg", 2, innocuous)?;
+    define_aggregate_function::<RegexUnionAgg>(db, "regex_union_agg", 1, innocuous)?;
+    define_aggregate_function::<RegexUnionAgg>(db, "regex_union_agg", 2, innocuous)?;
+    define_aggregate_function::<RegexUnionAgg>(db, "regex_union_agg", 3, innocuous)?;
+    define_window_function::<RegexFindWindow>(db, "regex_find_window", 2, innocuous)?;
+    define_window_function::<RegexFindWindow>(db, "regex_find_window", 3, innocuous)?;
+    define_scalar_function(db, "regex_captures_len", 1, regex_captures_len, innocuous)?;
+    define_scalar_function(db, "regex_group_index", 2, regex_group_index, innocuous)?;
+
+    define_scalar_function(db, "regex_builtin", 1, regex_builtin, innocuous)?;
+
+    define_scalar_function(db, "regexset", -1, regexset, innocuous)?;
+    define_scalar_function(db, "regexset_print", 1, regexset_print, innocuous)?;
+    define_scalar_function(db, "regexset_is_match", 2, regexset_is_match, innocuous)?;
+    define_scalar_function(db, "regex_redact", 2, regex_redact, innocuous)?;
+    define_scalar_function(db, "regex_redact", 3, regex_redact, innocuous)?;
+
+    define_scalar_function(db, "ac_set", -1, ac_set, innocuous)?;
+    define_scalar_function(
+        db,
+        "ac_set_with_options",
+        -1,
+        ac_set_with_options,
+        innocuous,
+    )?;
+    define_aggregate_function::<AcSetAgg>(db, "ac_set_agg", 1, innocuous)?;
+    define_aggregate_function::<AcSetAgg>(db, "ac_set_agg", 2, innocuous)?;
+    define_scalar_function(db, "ac_set_print", 1, ac_set_print, innocuous)?;
+    define_scalar_function(db, "ac_is_match", 2, ac_is_match, innocuous)?;
+    Ok(())
+}
+
+/// Functions that read or write the database, or change the connection, so
+/// they aren't pure scalars.
+fn regex_connection_init(db: *mut sqlite3) -> Result<()> {
+    let direct_only = FunctionFlags::UTF8 | FunctionFlags::DIRECTONLY;
+
+    define_scalar_function(db, "regex_define", 2, regex_define, direct_only)?;
+    define_scalar_function(db, "regex_define", 3, regex_define, direct_only)?;
+    define_scalar_function(db, "regex_undefine", 1, regex_undefine, direct_only)?;
+    define_scalar_function(db, "regex_named", 1, regex_named, FunctionFlags::UTF8)?;
+
+    define_scalar_function(
+        db,
+        "regex_create_collation",
+        3,
+        regex_create_collation,
+        direct_only,
+    )?;
+    define_scalar_function(
+        db,
+        "regex_replace_all_fn",
+        3,
+        regex_replace_all_fn,
+        direct_only,
+    )?;
+    Ok(())
+}
+
+/// Table functions without side effects.
+fn regex_safe_tables_init(db: *mut sqlite3) -> Result<()> {
+    define_table_function::<RegexBuiltinsTable>(db, "regex_builtins", None)?;
     define_table_function::<RegexFindAllTable>(db, "regex_find_all", None)?;
     define_table_function::<RegexSplitTable>(db, "regex_split", None)?;
     define_table_function_with_find::<RegexCapturesTable>(db, "regex_captures", None)?;
-
-    define_scalar_function(db, "regexset", -1, regexset, flags)?;
-    define_scalar_function(db, "regexset_print", 1, regexset_print, flags)?;
-    define_scalar_function(db, "regexset_is_match", 2, regexset_is_match, flags)?;
-
+    define_table_function::<RegexCaptureNamesTable>(db, "regex_capture_names", None)?;
     define_table_function::<RegexSetMatchesTable>(db, "regexset_matches", None)?;
+    define_table_function::<AcFindAllTable>(db, "ac_find_all", None)?;
+    Ok(())
+}
+
+/// Table functions that read from the filesystem.
+fn regex_filesystem_tables_init(db: *mut sqlite3) -> Result<()> {
+    define_table_function::<RegexReadLinesTable>(db, "regex_read_lines", None)?;
+    define_table_function::<RegexGrepTable>(db, "regex_grep", None)?;
     Ok(())
 }
@@ -0,0 +1,118 @@
+use regex_syntax::hir::{self, literal::Literals, Hir, HirKind};
+use serde_json::json;
+use sqlite_loadable::prelude::*;
+use sqlite_loadable::{api, Error, Result};
+
+use crate::utils::{cleanup_regex_value_cached, regex_from_value_or_cache};
+
+/// Decodes an extracted literal, dropping a trailing partial UTF-8 sequence
+/// left behind when the extractor cut the literal short.
+fn literal_text(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_owned(),
+        Err(err) => String::from_utf8_lossy(&bytes[..err.valid_up_to()]).into_owned(),
+    }
+}
+
+/// Like literal_text(), but for suffixes, which are cut short at the start,
+/// so a partial UTF-8 sequence shows up as leading continuation bytes.
+fn suffix_text(bytes: &[u8]) -> String {
+    let start = bytes
+        .iter()
+        .position(|b| b & 0xC0 != 0x80)
+        .unwrap_or(bytes.len());
+    literal_text(&bytes[start..])
+}
+
+fn literals_text(literals: &Literals, text: fn(&[u8]) -> String) -> Vec<String> {
+    literals
+        .literals()
+        .iter()
+        .map(|literal| text(literal))
+        .collect()
+}
+
+/// Collects the runs of literal text that every match must contain.
+/// `current` is the run being built, which is broken by anything that isn't
+/// a single character, like classes, optional repetitions or alternations.
+fn collect_required(node: &Hir, current: &mut String, required: &mut Vec<String>) {
+    let flush = |current: &mut String, required: &mut Vec<String>| {
+        if !current.is_empty() {
+            if !required.contains(current) {
+                required.push(current.clone());
+            }
+            current.clear();
+        }
+    };
+    match node.kind() {
+        HirKind::Literal(hir::Literal::Unicode(c)) => current.push(*c),
+        HirKind::Literal(hir::Literal::Byte(b)) if b.is_ascii() => current.push(*b as char),
+        HirKind::Class(hir::Class::Unicode(class))
+            if class.ranges().len() == 1
+                && class.ranges()[0].start() == class.ranges()[0].end() =>
+        {
+            current.push(class.ranges()[0].start())
+        }
+        // zero-width assertions don't break up a run of literals
+        HirKind::Empty | HirKind::Anchor(_) | HirKind::WordBoundary(_) => (),
+        HirKind::Group(group) => collect_required(&group.hir, current, required),
+        HirKind::Concat(nodes) => {
+            for node in nodes {
+                collect_required(node, current, required);
+            }
+        }
+        HirKind::Repetition(repetition) => {
+            flush(current, required);
+            let min = match &repetition.kind {
+                hir::RepetitionKind::ZeroOrOne | hir::RepetitionKind::ZeroOrMore => 0,
+                hir::RepetitionKind::OneOrMore => 1,
+                hir::RepetitionKind::Range(hir::RepetitionRange::Exactly(n))
+                | hir::RepetitionKind::Range(hir::RepetitionRange::AtLeast(n))
+                | hir::RepetitionKind::Range(hir::RepetitionRange::Bounded(n, _)) => *n,
+            };
+            if min > 0 {
+                collect_required(&repetition.hir, current, required);
+                flush(current, required);
+            }
+        }
+        _ => flush(current, required),
+    }
+}
+
+fn required_literals(hir: &Hir) -> Vec<String> {
+    let mut current = String::new();
+    let mut required = vec![];
+    collect_required(hir, &mut current, &mut required);
+    if !current.is_empty() && !required.contains(&current) {
+        required.push(current);
+    }
+    required
+}
+
+/// regex_literals(pattern)
+pub fn regex_literals(context: *mut sqlite3_context, values: &[*mut sqlite3_value]) -> Result<()> {
+    let (regex, input_type) = regex_from_value_or_cache(context, values, 0)?;
+    let hir = regex_syntax::Parser::new()
+        .parse(unsafe { &*regex }.as_str())
+        .map_err(|err| {
+            Error::new_message(format!("Error parsing pattern as regex: {}", err).as_str())
+        });
+    cleanup_regex_value_cached(context, regex, input_type);
+    let hir = hir?;
+    let prefixes = Literals::prefixes(&hir);
+    let suffixes = Literals::suffixes(&hir);
+    api::result_json(
+        context,
+        json!({
+            "prefix": literal_text(prefixes.longest_common_prefix()),
+            "suffix": suffix_text(suffixes.longest_common_suffix()),
+            "prefixes": literals_text(&prefixes, literal_text),
+            "suffixes": literals_text(&suffixes, suffix_text),
+            "required": required_literals(&hir),
+            "exact": prefixes.all_complete() && !prefixes.contains_empty(),
+            "anchored_start": hir.is_anchored_start(),
+            "anchored_end": hir.is_anchored_end(),
+        }),
+    )?;
+    Ok(())
+}
@@ -0,0 +1,156 @@
+use regex::Regex;
+use sqlite_loadable::prelude::*;
+use sqlite_loadable::{api, Error, Result};
+use std::os::raw::c_void;
+
+use crate::ext::query_row;
+use crate::functions;
+use crate::utils::{cleanup_regex, result_regex};
+
+static CREATE_PATTERNS_SQL: &str = "CREATE TABLE IF NOT EXISTS regex_patterns(
+  name text primary key,
+  pattern text not null,
+  flags text not null default ''
+)";
+
+/// Names are letters, digits and '_', and can't start with a digit.
+fn valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => (),
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Whether regex_define() has created the regex_patterns table yet.
+fn patterns_table_exists(db: *mut sqlite3) -> Result<bool> {
+    let row = query_row(
+        db,
+        "select 1 from sqlite_master where type = 'table' and name = 'regex_patterns'",
+        &[],
+    )?;
+    Ok(row.is_some())
+}
+
+/// Compiles the pattern saved under `name` with regex_define(), with its
+/// flags applied inline.
+fn named_regex(db: *mut sqlite3, name: &str) -> Result<Regex> {
+    let row = if patterns_table_exists(db)? {
+        query_row(
+            db,
+            "select pattern, flags from regex_patterns where name = ?",
+            &[name],
+        )?
+    } else {
+        None
+    };
+    let (pattern, flags) = match row.as_deref() {
+        Some([pattern, flags]) => (pattern, flags),
+        _ => {
+            return Err(Error::new_message(
+                format!(
+                    "regex pattern '@{}' is not defined, use regex_define() to define it",
+                    name
+                )
+                .as_str(),
+            ))
+        }
+    };
+    functions::regex(pattern, flags).map_err(|err| {
+        Error::new_message(format!("Error parsing pattern '@{}' as regex: {}", name, err).as_str())
+    })
+}
+
+fn name_arg<'a>(values: &[*mut sqlite3_value]) -> Result<&'a str> {
+    let name = api::value_text_notnull(
+        values
+            .first()
+            .ok_or_else(|| Error::new_message("expected 1st argument as name"))?,
+    )?;
+    let name = name.strip_prefix('@').unwrap_or(name);
+    if !valid_name(name) {
+        return Err(Error::new_message(
+            format!(
+                "invalid pattern name '{}', names must be letters, digits or '_', and can't start with a digit",
+                name
+            )
+            .as_str(),
+        ));
+    }
+    Ok(name)
+}
+
+/// regex_define(name, pattern [, flags])
+pub fn regex_define(context: *mut sqlite3_context, values: &[*mut sqlite3_value]) -> Result<()> {
+    let name = name_arg(values)?;
+    let pattern = api::value_text_notnull(
+        values
+            .get(1)
+            .ok_or_else(|| Error::new_message("expected 2nd argument as pattern"))?,
+    )?;
+    let flags = match values.get(2) {
+        Some(value) => api::value_text(value)?,
+        None => "",
+    };
+    functions::regex(pattern, flags).map_err(|err| {
+        Error::new_message(format!("Error parsing pattern as regex: {}", err).as_str())
+    })?;
+
+    let db = api::context_db_handle(context);
+    let saving_error = |err: Error| {
+        Error::new_message(
+            format!(
+                "Error saving pattern '@{}' into regex_patterns: {}",
+                name,
+                err.result_error_message()
+            )
+            .as_str(),
+        )
+    };
+    query_row(db, CREATE_PATTERNS_SQL, &[]).map_err(saving_error)?;
+    query_row(
+        db,
+        "insert or replace into regex_patterns(name, pattern, flags) values (?, ?, ?)",
+        &[name, pattern, flags],
+    )
+    .map_err(saving_error)?;
+    api::result_text(context, name)?;
+    Ok(())
+}
+
+/// regex_undefine(name)
+pub fn regex_undefine(context: *mut sqlite3_context, values: &[*mut sqlite3_value]) -> Result<()> {
+    let name = name_arg(values)?;
+    let db = api::context_db_handle(context);
+    // when regex_patterns doesn't exist yet, there's nothing to remove
+    let deleted = patterns_table_exists(db)?
+        && query_row(
+            db,
+            "delete from regex_patterns where name = ? returning name",
+            &[name],
+        )?
+        .is_some();
+    api::result_bool(context, deleted);
+    Ok(())
+}
+
+/// regex_named(name)
+pub fn regex_named(context: *mut sqlite3_context, values: &[*mut sqlite3_value]) -> Result<()> {
+    let auxdata = api::auxdata_get(context, 0);
+    let regex = if !auxdata.is_null() {
+        unsafe { &*auxdata.cast::<Regex>() }.clone()
+    } else {
+        let name = name_arg(values)?;
+        let regex = named_regex(api::context_db_handle(context), name)?;
+        api::auxdata_set(
+            context,
+            0,
+            Box::into_raw(Box::new(regex.clone())).cast::<c_void>(),
+            Some(cleanup_regex),
+        );
+        regex
+    };
+    result_regex(context, regex);
+    Ok(())
+}
@@ -0,0 +1,268 @@
+use regex::Regex;
+use sqlite_loadable::{
+    api,
+    table::{ConstraintOperator, IndexInfo, VTab, VTabArguments, VTabCursor},
+    BestIndexError, Result,
+};
+use sqlite_loadable::{prelude::*, Error};
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    mem,
+    os::raw::c_int,
+};
+
+use crate::ext::{vtab_config, VTabConfig};
+use crate::utils::{capture_groups, result_capture_groups, value_regex_owned, CaptureGroup};
+
+static CREATE_SQL: &str = "CREATE TABLE x(line_number int, line text, matched int, captures, path hidden, pattern hidden, matching_only hidden)";
+enum Columns {
+    LineNumber,
+    Line,
+    Matched,
+    Captures,
+    Path,
+    Pattern,
+    MatchingOnly,
+}
+fn column(index: i32) -> Option<Columns> {
+    match index {
+        0 => Some(Columns::LineNumber),
+        1 => Some(Columns::Line),
+        2 => Some(Columns::Matched),
+        3 => Some(Columns::Captures),
+        4 => Some(Columns::Path),
+        5 => Some(Columns::Pattern),
+        6 => Some(Columns::MatchingOnly),
+        _ => None,
+    }
+}
+
+/// Reads the next line from `reader` into `buf`, without the trailing
+/// newline. Returns false once the reader is exhausted. Invalid UTF-8
+/// is replaced rather than treated as an error, since log files often
+/// contain stray bytes.
+pub(crate) fn read_line<R: BufRead>(reader: &mut R, buf: &mut String) -> Result<bool> {
+    let mut bytes = vec![];
+    let n = reader
+        .read_until(b'\n', &mut bytes)
+        .map_err(|err| Error::new_message(format!("Error reading line: {}", err).as_str()))?;
+    if n == 0 {
+        return Ok(false);
+    }
+    if bytes.last() == Some(&b'\n') {
+        bytes.pop();
+        if bytes.last() == Some(&b'\r') {
+            bytes.pop();
+        }
+    }
+    buf.clear();
+    buf.push_str(&String::from_utf8_lossy(&bytes));
+    Ok(true)
+}
+
+#[repr(C)]
+pub struct RegexReadLinesTable {
+    /// must be first
+    base: sqlite3_vtab,
+}
+
+impl<'vtab> VTab<'vtab> for RegexReadLinesTable {
+    type Aux = ();
+    type Cursor = RegexReadLinesCursor;
+
+    fn connect(
+        db: *mut sqlite3,
+        _aux: Option<&Self::Aux>,
+        _args: VTabArguments,
+    ) -> Result<(String, RegexReadLinesTable)> {
+        let base: sqlite3_vtab = unsafe { mem::zeroed() };
+        let vtab = RegexReadLinesTable { base };
+        vtab_config(db, VTabConfig::DirectOnly)?;
+        Ok((CREATE_SQL.to_owned(), vtab))
+    }
+    fn destroy(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn best_index(&self, mut info: IndexInfo) -> core::result::Result<(), BestIndexError> {
+        let mut has_path = false;
+        let mut has_pattern = false;
+        let mut has_matching_only = false;
+        for mut constraint in info.constraints() {
+            match column(constraint.column_idx()) {
+                Some(Columns::Path) => {
+                    if constraint.usable() && constraint.op() == Some(ConstraintOperator::EQ) {
+                        constraint.set_omit(true);
+                        constraint.set_argv_index(1);
+                        has_path = true;
+                    } else {
+                        return Err(BestIndexError::Constraint);
+                    }
+                }
+                Some(Columns::Pattern) => {
+                    if constraint.usable() && constraint.op() == Some(ConstraintOperator::EQ) {
+                        constraint.set_omit(true);
+                        constraint.set_argv_index(2);
+                        has_pattern = true;
+                    } else {
+                        return Err(BestIndexError::Constraint);
+                    }
+                }
+                Some(Columns::MatchingOnly) => {
+                    if constraint.usable() && constraint.op() == Some(ConstraintOperator::EQ) {
+                        constraint.set_omit(true);
+                        constraint.set_argv_index(3);
+                        has_matching_only = true;
+                    } else {
+                        return Err(BestIndexError::Constraint);
+                    }
+                }
+                _ => (),
+            }
+        }
+        if !has_path || !has_pattern {
+            return Err(BestIndexError::Error);
+        }
+        info.set_estimated_cost(100000.0);
+        info.set_estimated_rows(100000);
+        info.set_idxnum(if has_matching_only { 3 } else { 2 });
+
+        Ok(())
+    }
+
+    fn open(&mut self) -> Result<RegexReadLinesCursor> {
+        Ok(RegexReadLinesCursor::new())
+    }
+}
+
+#[repr(C)]
+pub struct RegexReadLinesCursor {
+    /// Base class. Must be first
+    base: sqlite3_vtab_cursor,
+    reader: Option<BufReader<File>>,
+    regex: Option<Regex>,
+    matching_only: bool,
+    line_number: i64,
+    line: String,
+    /// The capture groups of the first match in `line`, if it matched.
+    captures: Option<Vec<CaptureGroup>>,
+    eof: bool,
+}
+impl RegexReadLinesCursor {
+    fn new() -> RegexReadLinesCursor {
+        let base: sqlite3_vtab_cursor = unsafe { mem::zeroed() };
+        RegexReadLinesCursor {
+            base,
+            reader: None,
+            regex: None,
+            matching_only: false,
+            line_number: 0,
+            line: String::new(),
+            captures: None,
+            eof: true,
+        }
+    }
+
+    /// Reads lines until one should be returned (any line, or only
+    /// matching lines when `matching_only` is set), or the file ends.
+    fn advance(&mut self) -> Result<()> {
+        let (reader, regex) = match (self.reader.as_mut(), self.regex.as_ref()) {
+            (Some(reader), Some(regex)) => (reader, regex),
+            _ => {
+                self.eof = true;
+                return Ok(());
+            }
+        };
+        loop {
+            if !read_line(reader, &mut self.line)? {
+                self.eof = true;
+                return Ok(());
+            }
+            self.line_number += 1;
+            self.captures = regex.captures(&self.line).map(|captures| {
+                let start = captures.get(0).map_or(0, |m| m.start());
+                let char_start = self.line[..start].chars().count();
+                capture_groups(regex, &captures, char_start)
+            });
+            if self.captures.is_some() || !self.matching_only {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl VTabCursor for RegexReadLinesCursor {
+    fn filter(
+        &mut self,
+        idx_num: c_int,
+        _idx_str: Option<&str>,
+        values: &[*mut sqlite3_value],
+    ) -> Result<()> {
+        let path = api::value_text_notnull(
+            values
+                .first()
+                .ok_or_else(|| Error::new_message("expected 1st argument as path"))?,
+        )?;
+        let regex = value_regex_owned(
+            values
+                .get(1)
+                .ok_or_else(|| Error::new_message("expected 2nd argument as regex"))?,
+        )?;
+        self.matching_only = if idx_num == 3 {
+            api::value_int64(
+                values
+                    .get(2)
+                    .ok_or_else(|| Error::new_message("expected 3rd argument as matching_only"))?,
+            ) != 0
+        } else {
+            false
+        };
+
+        let file = File::open(path).map_err(|err| {
+            Error::new_message(format!("Error opening {}: {}", path, err).as_str())
+        })?;
+        self.reader = Some(BufReader::new(file));
+        self.regex = Some(regex);
+        self.line_number = 0;
+        self.eof = false;
+        self.advance()
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.advance()
+    }
+
+    fn eof(&self) -> bool {
+        self.eof
+    }
+
+    fn column(&self, context: *mut sqlite3_context, i: c_int) -> Result<()> {
+        match column(i) {
+            Some(Columns::LineNumber) => {
+                api::result_int64(context, self.line_number);
+            }
+            Some(Columns::Line) => {
+                api::result_text(context, &self.line)?;
+            }
+            Some(Columns::Matched) => {
+                api::result_bool(context, self.captures.is_some());
+            }
+            Some(Columns::Captures) => match &self.captures {
+                Some(captures) => result_capture_groups(context, captures.clone()),
+                None => api::result_null(context),
+            },
+            Some(Columns::MatchingOnly) => {
+                api::result_bool(context, self.matching_only);
+            }
+            Some(Columns::Path) | Some(Columns::Pattern) => (),
+            None => (),
+        }
+        Ok(())
+    }
+
+    fn rowid(&self) -> Result<i64> {
+        Ok(self.line_number)
+    }
+}
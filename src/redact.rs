@@ -0,0 +1,155 @@
+use regex::{Regex, RegexSet};
+use sqlite_loadable::prelude::*;
+use sqlite_loadable::{api, Error, Result};
+
+use std::os::raw::c_void;
+
+use crate::utils::{cleanup_regex_value_cached, regex_from_value_or_cache, value_regexset};
+
+/// A pattern to redact, and the label used for `{label}` in the mask: the
+/// pattern's first named capture group, or its index in the set.
+struct Redaction {
+    regex: Regex,
+    label: String,
+}
+
+impl Redaction {
+    fn new(regex: Regex, index: usize) -> Redaction {
+        let label = regex
+            .capture_names()
+            .flatten()
+            .next()
+            .map_or_else(|| index.to_string(), str::to_owned);
+        Redaction { regex, label }
+    }
+}
+
+unsafe extern "C" fn cleanup_redactions(p: *mut c_void) {
+    drop(Box::from_raw(p.cast::<Vec<Redaction>>()))
+}
+
+fn compile_set(set: &RegexSet) -> Result<Vec<Redaction>> {
+    set.patterns()
+        .iter()
+        .enumerate()
+        .map(|(index, pattern)| {
+            Regex::new(pattern)
+                .map(|regex| Redaction::new(regex, index))
+                .map_err(|err| {
+                    Error::new_message(format!("Error parsing pattern as regex: {}", err).as_str())
+                })
+        })
+        .collect()
+}
+
+fn is_compiled_from(redactions: &[Redaction], set: &RegexSet) -> bool {
+    redactions
+        .iter()
+        .map(|redaction| redaction.regex.as_str())
+        .eq(set.patterns().iter().map(String::as_str))
+}
+
+/// The span of a match, and the label of the pattern that matched.
+struct Span<'a> {
+    start: usize,
+    end: usize,
+    label: &'a str,
+}
+
+/// Finds every non-empty match of every pattern. Overlapping matches are
+/// merged into one span, so no part of any match is left unredacted. A merged
+/// span keeps the label of the match that starts first, then the longest, then
+/// the pattern that comes first.
+fn find_spans<'a>(
+    redactions: &'a [Redaction],
+    indexes: impl Iterator<Item = usize>,
+    text: &str,
+) -> Vec<Span<'a>> {
+    let mut matches = vec![];
+    for index in indexes {
+        let redaction = &redactions[index];
+        for m in redaction.regex.find_iter(text) {
+            if m.start() < m.end() {
+                matches.push((m.start(), m.end(), index));
+            }
+        }
+    }
+    matches.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)).then(a.2.cmp(&b.2)));
+
+    let mut spans: Vec<Span> = vec![];
+    for (start, end, index) in matches {
+        match spans.last_mut() {
+            Some(last) if start < last.end => last.end = last.end.max(end),
+            _ => spans.push(Span {
+                start,
+                end,
+                label: &redactions[index].label,
+            }),
+        }
+    }
+    spans
+}
+
+fn redact(text: &str, spans: &[Span], mask: Option<&str>) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut last = 0;
+    for span in spans {
+        result.push_str(&text[last..span.start]);
+        match mask {
+            Some(mask) => result.push_str(&mask.replace("{label}", span.label)),
+            None => result.push_str(&"*".repeat(text[span.start..span.end].chars().count())),
+        }
+        last = span.end;
+    }
+    result.push_str(&text[last..]);
+    result
+}
+
+/// regex_redact(regexset_or_pattern, text [, mask])
+pub fn regex_redact(context: *mut sqlite3_context, values: &[*mut sqlite3_value]) -> Result<()> {
+    let text = api::value_text_notnull(
+        values
+            .get(1)
+            .ok_or_else(|| Error::new_message("expected 2nd argument as text"))?,
+    )?;
+    let mask = match values.get(2) {
+        Some(value) if !api::value_is_null(value) => Some(api::value_text(value)?),
+        _ => None,
+    };
+    let first = values
+        .first()
+        .ok_or_else(|| Error::new_message("expected 1st argument as regexset or pattern"))?;
+
+    let result = match value_regexset(first) {
+        Ok(set) => {
+            let set = unsafe { &*set };
+            // the set's patterns are compiled one by one for their matches,
+            // and cached for when the same set is used across many rows
+            let mut redactions = api::auxdata_get(context, 0).cast::<Vec<Redaction>>();
+            if redactions.is_null() || !is_compiled_from(unsafe { &*redactions }, set) {
+                redactions = Box::into_raw(Box::new(compile_set(set)?));
+                api::auxdata_set(
+                    context,
+                    0,
+                    redactions.cast::<c_void>(),
+                    Some(cleanup_redactions),
+                );
+                // SQLite calls the destructor right away if it can't keep it
+                redactions = api::auxdata_get(context, 0).cast::<Vec<Redaction>>();
+                if redactions.is_null() {
+                    return Err(Error::new_message("regex_redact: out of memory"));
+                }
+            }
+            let spans = find_spans(unsafe { &*redactions }, set.matches(text).iter(), text);
+            redact(text, &spans, mask)
+        }
+        Err(_) => {
+            let (regex, input_type) = regex_from_value_or_cache(context, values, 0)?;
+            let redactions = [Redaction::new(unsafe { &*regex }.clone(), 0)];
+            cleanup_regex_value_cached(context, regex, input_type);
+            redact(text, &find_spans(&redactions, 0..1, text), mask)
+        }
+    };
+    api::result_text(context, result)?;
+    Ok(())
+}
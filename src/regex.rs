@@ -1,8 +1,14 @@
 use regex::Regex;
+use regex_syntax::ast::{self, Ast};
+use regex_syntax::hir::{self, Hir, HirKind};
+use serde_json::{json, Value};
 
+use crate::functions::{self, Group};
+use crate::replacement::{template_from_cache, value_extended_mode};
 use crate::utils::{
-    cleanup_regex_value_cached, regex_from_value_or_cache, result_regex, value_regex,
-    value_regex_captures, CaptureGroupKey,
+    anchored_regex_from_value_or_cache, cleanup_regex_value_cached, find_capture_group,
+    regex_from_value_or_cache, result_regex, value_group, value_pattern, value_regex,
+    value_regex_captures,
 };
 use sqlite_loadable::prelude::*;
 use sqlite_loadable::{api, Error, Result};
@@ -15,10 +21,14 @@ pub fn regex_print(context: *mut sqlite3_context, values: &[*mut sqlite3_value])
     Ok(())
 }
 
-// regex(pattern)
+// regex(pattern [, flags])
 pub fn regex(context: *mut sqlite3_context, values: &[*mut sqlite3_value]) -> Result<()> {
     let pattern = api::value_text_notnull(values.get(0).ok_or("")?)?;
-    let regex = Regex::new(pattern).map_err(|err| {
+    let flags = match values.get(1) {
+        Some(value) => api::value_text(value)?,
+        None => "",
+    };
+    let regex = functions::regex(pattern, flags).map_err(|err| {
         Error::new_message(format!("Error parsing pattern as regex: {}", err).as_str())
     })?;
     result_regex(context, regex);
@@ -32,7 +42,7 @@ pub fn regex_matches(context: *mut sqlite3_context, values: &[*mut sqlite3_value
     let content =
         api::value_text_notnull(values.get(1).ok_or("expected 2nd argument as contents")?)?;
 
-    api::result_bool(context, regex.is_match(content));
+    api::result_bool(context, functions::regexp(regex, content));
     cleanup_regex_value_cached(context, regex, input_type);
     Ok(())
 }
@@ -50,7 +60,22 @@ pub fn regex_valid(context: *mut sqlite3_context, values: &[*mut sqlite3_value])
             .get(0)
             .ok_or_else(|| Error::new_message("expected 1st argument as pattern"))?,
     )?;
-    api::result_bool(context, Regex::new(pattern).is_ok());
+    api::result_bool(context, functions::regex_valid(pattern));
+    Ok(())
+}
+
+/// regex_full_match(regex, contents)
+pub fn regex_full_match(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    let content = api::value_text_notnull(
+        values
+            .get(1)
+            .ok_or_else(|| Error::new_message("expected 2nd argument as contents"))?,
+    )?;
+    let regex = anchored_regex_from_value_or_cache(context, values, 0)?;
+    api::result_bool(context, unsafe { &*regex }.is_match(content));
     Ok(())
 }
 
@@ -63,9 +88,9 @@ pub fn regex_find(context: *mut sqlite3_context, values: &[*mut sqlite3_value])
         .ok_or_else(|| Error::new_message("expected 2nd argument as contents"))?;
 
     let content = api::value_text_notnull(arg_content)?;
-    match regex.find(content) {
+    match functions::regex_find(regex, content) {
         Some(m) => {
-            api::result_text(context, m.as_str())?;
+            api::result_text(context, m)?;
         }
         None => {
             api::result_null(context);
@@ -76,6 +101,37 @@ pub fn regex_find(context: *mut sqlite3_context, values: &[*mut sqlite3_value])
     Ok(())
 }
 
+/// Reads the `n` argument of regex_find_nth() and regex_capture_nth().
+fn value_nth(value: Option<&*mut sqlite3_value>) -> Result<i64> {
+    let value = value.ok_or_else(|| Error::new_message("expected 3rd argument as n"))?;
+    if api::value_type(value) != api::ValueType::Integer {
+        return Err(Error::new_message("expected n to be an integer"));
+    }
+    match api::value_int64(value) {
+        0 => Err(Error::new_message(
+            "n is 1-based, use 1 for the first match or -1 for the last",
+        )),
+        n => Ok(n),
+    }
+}
+
+/// regex_find_nth(regex, contents, n)
+pub fn regex_find_nth(context: *mut sqlite3_context, values: &[*mut sqlite3_value]) -> Result<()> {
+    let content = api::value_text_notnull(
+        values
+            .get(1)
+            .ok_or_else(|| Error::new_message("expected 2nd argument as contents"))?,
+    )?;
+    let n = value_nth(values.get(2))?;
+    let (regex, input_type) = regex_from_value_or_cache(context, values, 0)?;
+    match functions::regex_find_nth(unsafe { &*regex }, content, n) {
+        Some(m) => api::result_text(context, m)?,
+        None => api::result_null(context),
+    }
+    cleanup_regex_value_cached(context, regex, input_type);
+    Ok(())
+}
+
 /// regex_find_at(regex, contents, offset)
 pub fn regex_find_at(context: *mut sqlite3_context, values: &[*mut sqlite3_value]) -> Result<()> {
     let (regex, input_type) = regex_from_value_or_cache(context, values, 0)?;
@@ -103,7 +159,7 @@ pub fn regex_find_at(context: *mut sqlite3_context, values: &[*mut sqlite3_value
     Ok(())
 }
 
-/// regex_replace(regex, contents, replacement)
+/// regex_replace(regex, contents, replacement [, mode])
 pub fn regex_replace(context: *mut sqlite3_context, values: &[*mut sqlite3_value]) -> Result<()> {
     let (regex, input_type) = regex_from_value_or_cache(context, values, 0)?;
     let regex = unsafe { &mut *regex };
@@ -118,7 +174,11 @@ pub fn regex_replace(context: *mut sqlite3_context, values: &[*mut sqlite3_value
             .ok_or_else(|| Error::new_message("expected 3rd argument as replacement"))?,
     )?;
 
-    let result = regex.replace(content, replacement);
+    let result = if value_extended_mode(values.get(3))? {
+        regex.replace(content, template_from_cache(context, replacement, 2)?)
+    } else {
+        functions::regex_replace(regex, content, replacement)
+    };
 
     api::result_text(context, result)?;
     cleanup_regex_value_cached(context, regex, input_type);
@@ -126,7 +186,7 @@ pub fn regex_replace(context: *mut sqlite3_context, values: &[*mut sqlite3_value
     Ok(())
 }
 
-/// regex_replace_all(regex, contents, replacement)
+/// regex_replace_all(regex, contents, replacement [, mode])
 pub fn regex_replace_all(
     context: *mut sqlite3_context,
     values: &[*mut sqlite3_value],
@@ -143,7 +203,11 @@ pub fn regex_replace_all(
             .get(2)
             .ok_or_else(|| Error::new_message("expected 3rd argument as replacement"))?,
     )?;
-    let result = regex.replace_all(content, replacement);
+    let result = if value_extended_mode(values.get(3))? {
+        regex.replace_all(content, template_from_cache(context, replacement, 2)?)
+    } else {
+        functions::regex_replace_all(regex, content, replacement)
+    };
     api::result_text(context, result)?;
 
     cleanup_regex_value_cached(context, regex, input_type);
@@ -159,33 +223,46 @@ pub fn regex_capture(context: *mut sqlite3_context, values: &[*mut sqlite3_value
             .get(1)
             .ok_or_else(|| Error::new_message("expected 2nd argument as contents"))?,
     )?;
-    let group_arg = values
-        .get(2)
-        .ok_or_else(|| Error::new_message("expected 3rd argument as group index or name"))?;
-
-    let result = regex.captures(content);
-    match result {
+    let group = value_group(
+        values
+            .get(2)
+            .ok_or_else(|| Error::new_message("expected 3rd argument as group index or name"))?,
+    )?;
+    match functions::regex_capture(regex, content, group) {
         None => api::result_null(context),
-        Some(captures) => {
-            let matched_capture = match api::value_type(group_arg) {
-                api::ValueType::Integer => captures.get(api::value_int64(group_arg) as usize),
-                _ => {
-                    let name = api::value_text(group_arg)?;
-                    captures.name(name)
-                }
-            };
-            match matched_capture {
-                None => api::result_null(context),
-                Some(matched_group) => {
-                    api::result_text(context, matched_group.as_str())?;
-                }
-            }
+        Some(matched_group) => {
+            api::result_text(context, matched_group)?;
         }
     }
     cleanup_regex_value_cached(context, regex, input_type);
     Ok(())
 }
 
+/// regex_capture_nth(regex, contents, n, group)
+pub fn regex_capture_nth(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    let content = api::value_text_notnull(
+        values
+            .get(1)
+            .ok_or_else(|| Error::new_message("expected 2nd argument as contents"))?,
+    )?;
+    let n = value_nth(values.get(2))?;
+    let group = value_group(
+        values
+            .get(3)
+            .ok_or_else(|| Error::new_message("expected 4th argument as group index or name"))?,
+    )?;
+    let (regex, input_type) = regex_from_value_or_cache(context, values, 0)?;
+    match functions::regex_capture_nth(unsafe { &*regex }, content, n, group) {
+        Some(m) => api::result_text(context, m)?,
+        None => api::result_null(context),
+    }
+    cleanup_regex_value_cached(context, regex, input_type);
+    Ok(())
+}
+
 /// regex_capture(captures, group)
 pub fn regex_capture2(context: *mut sqlite3_context, values: &[*mut sqlite3_value]) -> Result<()> {
     let captures = value_regex_captures(
@@ -194,32 +271,12 @@ pub fn regex_capture2(context: *mut sqlite3_context, values: &[*mut sqlite3_valu
             .ok_or_else(|| Error::new_message("expected 1st argument as capture group"))?,
     )?;
     let captures = unsafe { &*captures };
-    let group_arg = values
-        .get(1)
-        .ok_or_else(|| Error::new_message("expected 3rd argument as group index or name"))?;
-
-    let matched_capture = match api::value_type(group_arg) {
-        api::ValueType::Integer => {
-            let lookup = api::value_int64(group_arg) as usize;
-            captures.iter().find(|c| {
-                if let CaptureGroupKey::Index(idx) = c.key {
-                    idx == lookup
-                } else {
-                    false
-                }
-            })
-        }
-        _ => {
-            let name = api::value_text(group_arg)?;
-            captures.iter().find(|c| {
-                if let CaptureGroupKey::Name(n) = &c.key {
-                    name == n
-                } else {
-                    false
-                }
-            })
-        }
-    };
+    let group = value_group(
+        values
+            .get(1)
+            .ok_or_else(|| Error::new_message("expected 2nd argument as group index or name"))?,
+    )?;
+    let matched_capture = find_capture_group(captures, group);
     match matched_capture {
         None => api::result_null(context),
         Some(m) => match &m.value {
@@ -229,3 +286,431 @@ pub fn regex_capture2(context: *mut sqlite3_context, values: &[*mut sqlite3_valu
     }
     Ok(())
 }
+
+/// regex_extract_all(pattern, text [, group])
+pub fn regex_extract_all(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    let content = api::value_text_notnull(
+        values
+            .get(1)
+            .ok_or_else(|| Error::new_message("expected 2nd argument as contents"))?,
+    )?;
+    let group = match values.get(2) {
+        None => Group::Index(0),
+        Some(group_arg) => value_group(group_arg)?,
+    };
+    let (regex, input_type) = regex_from_value_or_cache(context, values, 0)?;
+    let result = extract_all(unsafe { &*regex }, content, group);
+    cleanup_regex_value_cached(context, regex, input_type);
+    api::result_json(context, result?)
+}
+
+fn extract_all(regex: &Regex, content: &str, group: Group) -> Result<Value> {
+    // unlike regex_capture(), a group that can't exist is an error rather than
+    // an array of nulls
+    let exists = match group {
+        Group::Index(index) => index < regex.captures_len(),
+        Group::Name(name) => regex.capture_names().flatten().any(|n| n == name),
+    };
+    if !exists {
+        let message = match group {
+            Group::Index(index) => format!("no capture group at index {} in pattern", index),
+            Group::Name(name) => format!("no capture group named '{}' in pattern", name),
+        };
+        return Err(Error::new_message(message.as_str()));
+    }
+    Ok(Value::Array(
+        functions::regex_extract_all(regex, content, group)
+            .into_iter()
+            .map(|value| value.map_or(Value::Null, Value::from))
+            .collect(),
+    ))
+}
+
+fn span_json(span: &ast::Span) -> Value {
+    json!({"start": span.start.offset, "end": span.end.offset})
+}
+
+fn ast_node(node_type: &str, span: &ast::Span, children: Vec<Value>) -> Value {
+    json!({"type": node_type, "span": span_json(span), "children": children})
+}
+
+fn repetition_bounds(range: &ast::RepetitionRange) -> (u32, Option<u32>) {
+    match *range {
+        ast::RepetitionRange::Exactly(n) => (n, Some(n)),
+        ast::RepetitionRange::AtLeast(n) => (n, None),
+        ast::RepetitionRange::Bounded(min, max) => (min, Some(max)),
+    }
+}
+
+fn class_perl_name(class: &ast::ClassPerl) -> &'static str {
+    match class.kind {
+        ast::ClassPerlKind::Digit => "digit",
+        ast::ClassPerlKind::Space => "space",
+        ast::ClassPerlKind::Word => "word",
+    }
+}
+
+fn class_unicode_name(class: &ast::ClassUnicode) -> String {
+    match &class.kind {
+        ast::ClassUnicodeKind::OneLetter(c) => c.to_string(),
+        ast::ClassUnicodeKind::Named(name) => name.clone(),
+        ast::ClassUnicodeKind::NamedValue { name, value, .. } => format!("{}={}", name, value),
+    }
+}
+
+fn ast_class_set_item_json(pattern: &str, item: &ast::ClassSetItem) -> Value {
+    match item {
+        ast::ClassSetItem::Empty(span) => ast_node("empty", span, vec![]),
+        ast::ClassSetItem::Literal(literal) => {
+            let mut node = ast_node("literal", &literal.span, vec![]);
+            node["value"] = json!(literal.c.to_string());
+            node
+        }
+        ast::ClassSetItem::Range(range) => {
+            let mut node = ast_node("range", &range.span, vec![]);
+            node["start"] = json!(range.start.c.to_string());
+            node["end"] = json!(range.end.c.to_string());
+            node
+        }
+        ast::ClassSetItem::Ascii(class) => {
+            let mut node = ast_node("class_ascii", &class.span, vec![]);
+            node["name"] = json!(pattern[class.span.start.offset..class.span.end.offset]
+                .trim_start_matches("[:")
+                .trim_start_matches('^')
+                .trim_end_matches(":]"));
+            node["negated"] = json!(class.negated);
+            node
+        }
+        ast::ClassSetItem::Unicode(class) => ast_class_unicode_json(class),
+        ast::ClassSetItem::Perl(class) => ast_class_perl_json(class),
+        ast::ClassSetItem::Bracketed(class) => ast_class_bracketed_json(pattern, class),
+        ast::ClassSetItem::Union(union) => ast_node(
+            "union",
+            &union.span,
+            union
+                .items
+                .iter()
+                .map(|item| ast_class_set_item_json(pattern, item))
+                .collect(),
+        ),
+    }
+}
+
+fn ast_class_set_json(pattern: &str, set: &ast::ClassSet) -> Value {
+    match set {
+        ast::ClassSet::Item(item) => ast_class_set_item_json(pattern, item),
+        ast::ClassSet::BinaryOp(op) => {
+            let mut node = ast_node(
+                "class_set_op",
+                &op.span,
+                vec![
+                    ast_class_set_json(pattern, &op.lhs),
+                    ast_class_set_json(pattern, &op.rhs),
+                ],
+            );
+            node["op"] = json!(match op.kind {
+                ast::ClassSetBinaryOpKind::Intersection => "intersection",
+                ast::ClassSetBinaryOpKind::Difference => "difference",
+                ast::ClassSetBinaryOpKind::SymmetricDifference => "symmetric_difference",
+            });
+            node
+        }
+    }
+}
+
+fn ast_class_unicode_json(class: &ast::ClassUnicode) -> Value {
+    let mut node = ast_node("class_unicode", &class.span, vec![]);
+    node["name"] = json!(class_unicode_name(class));
+    node["negated"] = json!(class.negated);
+    node
+}
+
+fn ast_class_perl_json(class: &ast::ClassPerl) -> Value {
+    let mut node = ast_node("class_perl", &class.span, vec![]);
+    node["name"] = json!(class_perl_name(class));
+    node["negated"] = json!(class.negated);
+    node
+}
+
+fn ast_class_bracketed_json(pattern: &str, class: &ast::ClassBracketed) -> Value {
+    let mut node = ast_node(
+        "class_bracketed",
+        &class.span,
+        vec![ast_class_set_json(pattern, &class.kind)],
+    );
+    node["negated"] = json!(class.negated);
+    node
+}
+
+fn ast_json(pattern: &str, node: &Ast) -> Value {
+    match node {
+        Ast::Empty(span) => ast_node("empty", span, vec![]),
+        Ast::Flags(flags) => {
+            let mut node = ast_node("flags", &flags.span, vec![]);
+            node["flags"] =
+                json!(&pattern[flags.flags.span.start.offset..flags.flags.span.end.offset]);
+            node
+        }
+        Ast::Literal(literal) => {
+            let mut node = ast_node("literal", &literal.span, vec![]);
+            node["value"] = json!(literal.c.to_string());
+            node
+        }
+        Ast::Dot(span) => ast_node("dot", span, vec![]),
+        Ast::Assertion(assertion) => {
+            let mut node = ast_node("assertion", &assertion.span, vec![]);
+            node["kind"] = json!(match assertion.kind {
+                ast::AssertionKind::StartLine => "start_line",
+                ast::AssertionKind::EndLine => "end_line",
+                ast::AssertionKind::StartText => "start_text",
+                ast::AssertionKind::EndText => "end_text",
+                ast::AssertionKind::WordBoundary => "word_boundary",
+                ast::AssertionKind::NotWordBoundary => "not_word_boundary",
+            });
+            node
+        }
+        Ast::Class(ast::Class::Unicode(class)) => ast_class_unicode_json(class),
+        Ast::Class(ast::Class::Perl(class)) => ast_class_perl_json(class),
+        Ast::Class(ast::Class::Bracketed(class)) => ast_class_bracketed_json(pattern, class),
+        Ast::Repetition(repetition) => {
+            let mut node = ast_node(
+                "repetition",
+                &repetition.span,
+                vec![ast_json(pattern, &repetition.ast)],
+            );
+            let (min, max) = match &repetition.op.kind {
+                ast::RepetitionKind::ZeroOrOne => (0, Some(1)),
+                ast::RepetitionKind::ZeroOrMore => (0, None),
+                ast::RepetitionKind::OneOrMore => (1, None),
+                ast::RepetitionKind::Range(range) => repetition_bounds(range),
+            };
+            node["min"] = json!(min);
+            node["max"] = json!(max);
+            node["greedy"] = json!(repetition.greedy);
+            node
+        }
+        Ast::Group(group) => {
+            let mut node = ast_node("group", &group.span, vec![ast_json(pattern, &group.ast)]);
+            match &group.kind {
+                ast::GroupKind::CaptureIndex(index) => {
+                    node["capture_index"] = json!(index);
+                }
+                ast::GroupKind::CaptureName(name) => {
+                    node["capture_index"] = json!(name.index);
+                    node["capture_name"] = json!(name.name);
+                }
+                ast::GroupKind::NonCapturing(flags) => {
+                    node["capture_index"] = Value::Null;
+                    node["flags"] = json!(&pattern[flags.span.start.offset..flags.span.end.offset]);
+                }
+            }
+            node
+        }
+        Ast::Alternation(alternation) => ast_node(
+            "alternation",
+            &alternation.span,
+            alternation
+                .asts
+                .iter()
+                .map(|node| ast_json(pattern, node))
+                .collect(),
+        ),
+        Ast::Concat(concat) => ast_node(
+            "concat",
+            &concat.span,
+            concat
+                .asts
+                .iter()
+                .map(|node| ast_json(pattern, node))
+                .collect(),
+        ),
+    }
+}
+
+fn hir_node(node_type: &str, children: Vec<Value>) -> Value {
+    json!({"type": node_type, "children": children})
+}
+
+fn hir_json(node: &Hir) -> Value {
+    match node.kind() {
+        HirKind::Empty => hir_node("empty", vec![]),
+        HirKind::Literal(literal) => {
+            let mut node = hir_node("literal", vec![]);
+            node["value"] = match literal {
+                hir::Literal::Unicode(c) => json!(c.to_string()),
+                hir::Literal::Byte(b) => json!(b),
+            };
+            node
+        }
+        HirKind::Class(hir::Class::Unicode(class)) => {
+            let mut node = hir_node("class", vec![]);
+            node["unicode"] = json!(!class.is_all_ascii());
+            node["ranges"] = class
+                .iter()
+                .map(|range| json!([range.start().to_string(), range.end().to_string()]))
+                .collect();
+            node
+        }
+        HirKind::Class(hir::Class::Bytes(class)) => {
+            let mut node = hir_node("class_bytes", vec![]);
+            node["unicode"] = json!(false);
+            node["ranges"] = class
+                .iter()
+                .map(|range| json!([range.start(), range.end()]))
+                .collect();
+            node
+        }
+        HirKind::Anchor(anchor) => {
+            let mut node = hir_node("anchor", vec![]);
+            node["kind"] = json!(match anchor {
+                hir::Anchor::StartLine => "start_line",
+                hir::Anchor::EndLine => "end_line",
+                hir::Anchor::StartText => "start_text",
+                hir::Anchor::EndText => "end_text",
+            });
+            node
+        }
+        HirKind::WordBoundary(boundary) => {
+            let mut node = hir_node("word_boundary", vec![]);
+            node["unicode"] = json!(matches!(
+                boundary,
+                hir::WordBoundary::Unicode | hir::WordBoundary::UnicodeNegate
+            ));
+            node["negated"] = json!(boundary.is_negated());
+            node
+        }
+        HirKind::Repetition(repetition) => {
+            let mut node = hir_node("repetition", vec![hir_json(&repetition.hir)]);
+            let (min, max) = match &repetition.kind {
+                hir::RepetitionKind::ZeroOrOne => (0, Some(1)),
+                hir::RepetitionKind::ZeroOrMore => (0, None),
+                hir::RepetitionKind::OneOrMore => (1, None),
+                hir::RepetitionKind::Range(hir::RepetitionRange::Exactly(n)) => (*n, Some(*n)),
+                hir::RepetitionKind::Range(hir::RepetitionRange::AtLeast(n)) => (*n, None),
+                hir::RepetitionKind::Range(hir::RepetitionRange::Bounded(min, max)) => {
+                    (*min, Some(*max))
+                }
+            };
+            node["min"] = json!(min);
+            node["max"] = json!(max);
+            node["greedy"] = json!(repetition.greedy);
+            node
+        }
+        HirKind::Group(group) => {
+            let mut node = hir_node("group", vec![hir_json(&group.hir)]);
+            match &group.kind {
+                hir::GroupKind::CaptureIndex(index) => {
+                    node["capture_index"] = json!(index);
+                }
+                hir::GroupKind::CaptureName { name, index } => {
+                    node["capture_index"] = json!(index);
+                    node["capture_name"] = json!(name);
+                }
+                hir::GroupKind::NonCapturing => {
+                    node["capture_index"] = Value::Null;
+                }
+            }
+            node
+        }
+        HirKind::Concat(nodes) => hir_node("concat", nodes.iter().map(hir_json).collect()),
+        HirKind::Alternation(nodes) => {
+            hir_node("alternation", nodes.iter().map(hir_json).collect())
+        }
+    }
+}
+
+#[derive(Default)]
+struct HirFeatures {
+    unicode_classes: usize,
+    unicode_word_boundaries: usize,
+    line_anchors: usize,
+    captures: usize,
+}
+
+fn collect_hir_features(node: &Hir, features: &mut HirFeatures) {
+    match node.kind() {
+        HirKind::Class(hir::Class::Unicode(class)) if !class.is_all_ascii() => {
+            features.unicode_classes += 1
+        }
+        HirKind::WordBoundary(hir::WordBoundary::Unicode)
+        | HirKind::WordBoundary(hir::WordBoundary::UnicodeNegate) => {
+            features.unicode_word_boundaries += 1
+        }
+        HirKind::Anchor(hir::Anchor::StartLine) | HirKind::Anchor(hir::Anchor::EndLine) => {
+            features.line_anchors += 1
+        }
+        HirKind::Repetition(repetition) => collect_hir_features(&repetition.hir, features),
+        HirKind::Group(group) => {
+            if !matches!(group.kind, hir::GroupKind::NonCapturing) {
+                features.captures += 1;
+            }
+            collect_hir_features(&group.hir, features)
+        }
+        HirKind::Concat(nodes) | HirKind::Alternation(nodes) => {
+            for node in nodes {
+                collect_hir_features(node, features);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Properties of the translated pattern that affect how fast it runs:
+/// Unicode classes and word boundaries compile into much larger automata,
+/// and patterns anchored at the start can skip scanning the whole text.
+fn hir_features_json(hir: &Hir) -> Value {
+    let mut features = HirFeatures::default();
+    collect_hir_features(hir, &mut features);
+    json!({
+        "anchored_start": hir.is_anchored_start(),
+        "anchored_end": hir.is_anchored_end(),
+        "line_anchors": features.line_anchors,
+        "unicode_classes": features.unicode_classes,
+        "unicode_word_boundaries": features.unicode_word_boundaries,
+        "captures": features.captures,
+        "literal": hir.is_literal(),
+        "match_empty": hir.is_match_empty(),
+    })
+}
+
+fn parse_hir(pattern: &str) -> Result<Hir> {
+    regex_syntax::Parser::new().parse(pattern).map_err(|err| {
+        Error::new_message(format!("Error parsing pattern as regex: {}", err).as_str())
+    })
+}
+
+/// regex_ast(pattern)
+pub fn regex_ast(context: *mut sqlite3_context, values: &[*mut sqlite3_value]) -> Result<()> {
+    let pattern = value_pattern(
+        values
+            .first()
+            .ok_or_else(|| Error::new_message("expected 1st argument as pattern"))?,
+    )?;
+    let ast = ast::parse::Parser::new().parse(pattern).map_err(|err| {
+        Error::new_message(format!("Error parsing pattern as regex: {}", err).as_str())
+    })?;
+    let hir = parse_hir(pattern)?;
+    api::result_json(
+        context,
+        json!({"ast": ast_json(pattern, &ast), "features": hir_features_json(&hir)}),
+    )?;
+    Ok(())
+}
+
+/// regex_hir(pattern)
+pub fn regex_hir(context: *mut sqlite3_context, values: &[*mut sqlite3_value]) -> Result<()> {
+    let pattern = value_pattern(
+        values
+            .first()
+            .ok_or_else(|| Error::new_message("expected 1st argument as pattern"))?,
+    )?;
+    let hir = parse_hir(pattern)?;
+    api::result_json(
+        context,
+        json!({"hir": hir_json(&hir), "features": hir_features_json(&hir)}),
+    )?;
+    Ok(())
+}
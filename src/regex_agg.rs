@@ -0,0 +1,301 @@
+use regex::Regex;
+use serde_json::Value;
+use sqlite_loadable::prelude::*;
+use sqlite_loadable::{api, Error, Result};
+use std::collections::{HashMap, VecDeque};
+
+use crate::aggregate::{Aggregate, WindowAggregate};
+use crate::utils::{result_regex, value_regex_owned};
+
+/// Compiles the pattern on the first row of a group, and reuses it for the
+/// rest of the group.
+fn group_regex<'a>(
+    regex: &'a mut Option<Regex>,
+    values: &[*mut sqlite3_value],
+) -> Result<&'a Regex> {
+    if regex.is_none() {
+        let value = values
+            .first()
+            .ok_or_else(|| Error::new_message("expected 1st argument as pattern"))?;
+        *regex = Some(value_regex_owned(value)?);
+    }
+    regex
+        .as_ref()
+        .ok_or_else(|| Error::new_message("sqlite-regex internal error: regex not compiled"))
+}
+
+/// regex_matches_agg(pattern, text)
+#[derive(Default)]
+pub(crate) struct RegexMatchesAgg {
+    regex: Option<Regex>,
+    matches: Vec<Value>,
+}
+
+impl Aggregate for RegexMatchesAgg {
+    fn step(
+        &mut self,
+        _context: *mut sqlite3_context,
+        values: &[*mut sqlite3_value],
+    ) -> Result<()> {
+        let text = values
+            .get(1)
+            .ok_or_else(|| Error::new_message("expected 2nd argument as text"))?;
+        if api::value_is_null(text) {
+            return Ok(());
+        }
+        let text = api::value_text(text)?;
+        let regex = group_regex(&mut self.regex, values)?;
+        self.matches
+            .extend(regex.find_iter(text).map(|m| Value::from(m.as_str())));
+        Ok(())
+    }
+
+    fn result(&self, context: *mut sqlite3_context) -> Result<()> {
+        api::result_json(context, Value::Array(self.matches.clone()))
+    }
+}
+
+/// regex_count_agg(pattern, text)
+#[derive(Default)]
+pub(crate) struct RegexCountAgg {
+    regex: Option<Regex>,
+    count: i64,
+}
+
+impl Aggregate for RegexCountAgg {
+    fn step(
+        &mut self,
+        _context: *mut sqlite3_context,
+        values: &[*mut sqlite3_value],
+    ) -> Result<()> {
+        let text = values
+            .get(1)
+            .ok_or_else(|| Error::new_message("expected 2nd argument as text"))?;
+        if api::value_is_null(text) {
+            return Ok(());
+        }
+        let text = api::value_text(text)?;
+        let regex = group_regex(&mut self.regex, values)?;
+        self.count += regex.find_iter(text).count() as i64;
+        Ok(())
+    }
+
+    fn result(&self, context: *mut sqlite3_context) -> Result<()> {
+        api::result_int64(context, self.count);
+        Ok(())
+    }
+}
+
+struct UnionBranch {
+    pattern: String,
+    literal: bool,
+    name: Option<String>,
+}
+
+impl UnionBranch {
+    fn source(&self) -> String {
+        if self.literal {
+            regex::escape(&self.pattern)
+        } else {
+            self.pattern.clone()
+        }
+    }
+}
+
+/// Whether `name` can be used as a capture group name, with the same rules
+/// as the regex crate's parser.
+fn valid_group_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => (),
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '[' | ']'))
+}
+
+/// regex_union_agg(pattern [, is_literal [, group_name]])
+#[derive(Default)]
+pub(crate) struct RegexUnionAgg {
+    branches: Vec<UnionBranch>,
+}
+
+impl RegexUnionAgg {
+    /// Literal branches come first, longest first, so the leftmost-first
+    /// alternation prefers "foobar" over "foo". Pattern branches follow in
+    /// the order they were added. Branches with the same name share one
+    /// capture group, which sits where its first branch would be.
+    fn pattern(&self) -> String {
+        let mut branches: Vec<&UnionBranch> = self.branches.iter().collect();
+        branches.sort_by_key(|branch| {
+            if branch.literal {
+                -(branch.pattern.chars().count() as i64)
+            } else {
+                1
+            }
+        });
+        let mut groups: Vec<(Option<&str>, Vec<&UnionBranch>)> = vec![];
+        let mut named_groups: HashMap<&str, usize> = HashMap::new();
+        for branch in branches {
+            match branch.name.as_deref() {
+                Some(name) => match named_groups.get(name) {
+                    Some(&index) => groups[index].1.push(branch),
+                    None => {
+                        named_groups.insert(name, groups.len());
+                        groups.push((Some(name), vec![branch]));
+                    }
+                },
+                None => groups.push((None, vec![branch])),
+            }
+        }
+        groups
+            .iter()
+            .map(|(name, members)| match name {
+                Some(name) => {
+                    let alternation = members
+                        .iter()
+                        .map(|branch| {
+                            if branch.literal {
+                                branch.source()
+                            } else {
+                                // keeps inline flags like (?i) inside their branch
+                                format!("(?:{})", branch.pattern)
+                            }
+                        })
+                        .collect::<Vec<String>>()
+                        .join("|");
+                    format!("(?P<{}>{})", name, alternation)
+                }
+                None => format!("(?:{})", members[0].source()),
+            })
+            .collect::<Vec<String>>()
+            .join("|")
+    }
+}
+
+impl Aggregate for RegexUnionAgg {
+    fn step(
+        &mut self,
+        _context: *mut sqlite3_context,
+        values: &[*mut sqlite3_value],
+    ) -> Result<()> {
+        let pattern = values
+            .first()
+            .ok_or_else(|| Error::new_message("expected 1st argument as pattern"))?;
+        if api::value_is_null(pattern) {
+            return Ok(());
+        }
+        let pattern = api::value_text(pattern)?;
+        let literal = values
+            .get(1)
+            .map(|value| api::value_int64(value) != 0)
+            .unwrap_or(false);
+        let name = match values.get(2) {
+            Some(value) if !api::value_is_null(value) => Some(api::value_text(value)?.to_owned()),
+            _ => None,
+        };
+        if let Some(name) = &name {
+            if !valid_group_name(name) {
+                return Err(Error::new_message(
+                    format!(
+                        "invalid group name '{}', group names must start with a letter or '_', and only contain letters, digits, '_', '.', '[' or ']'",
+                        name
+                    )
+                    .as_str(),
+                ));
+            }
+        }
+        let pattern = pattern.to_owned();
+        let duplicate = self.branches.iter().any(|branch| {
+            branch.pattern == pattern && branch.literal == literal && branch.name == name
+        });
+        if !duplicate {
+            self.branches.push(UnionBranch {
+                pattern,
+                literal,
+                name,
+            });
+        }
+        Ok(())
+    }
+
+    fn result(&self, context: *mut sqlite3_context) -> Result<()> {
+        if self.branches.is_empty() {
+            api::result_null(context);
+            return Ok(());
+        }
+        let regex = Regex::new(&self.pattern()).map_err(|err| {
+            Error::new_message(
+                format!("Error parsing union of patterns as regex: {}", err).as_str(),
+            )
+        })?;
+        result_regex(context, regex);
+        Ok(())
+    }
+}
+
+/// regex_find_window(pattern, text [, separator])
+#[derive(Default)]
+pub(crate) struct RegexFindWindow {
+    regex: Option<Regex>,
+    separator: Option<String>,
+    rows: VecDeque<String>,
+}
+
+impl Aggregate for RegexFindWindow {
+    fn step(
+        &mut self,
+        _context: *mut sqlite3_context,
+        values: &[*mut sqlite3_value],
+    ) -> Result<()> {
+        group_regex(&mut self.regex, values)?;
+        if self.separator.is_none() {
+            self.separator = Some(match values.get(2) {
+                Some(value) => api::value_text(value)?.to_owned(),
+                None => "\n".to_owned(),
+            });
+        }
+        let text = values
+            .get(1)
+            .ok_or_else(|| Error::new_message("expected 2nd argument as text"))?;
+        if !api::value_is_null(text) {
+            self.rows.push_back(api::value_text(text)?.to_owned());
+        }
+        Ok(())
+    }
+
+    fn result(&self, context: *mut sqlite3_context) -> Result<()> {
+        let regex = match &self.regex {
+            Some(regex) => regex,
+            None => return api::result_json(context, Value::Array(vec![])),
+        };
+        let separator = self.separator.as_deref().unwrap_or("\n");
+        let text = self
+            .rows
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<&str>>()
+            .join(separator);
+        let matches = regex
+            .find_iter(&text)
+            .map(|m| Value::from(m.as_str()))
+            .collect();
+        api::result_json(context, Value::Array(matches))
+    }
+}
+
+impl WindowAggregate for RegexFindWindow {
+    fn inverse(
+        &mut self,
+        _context: *mut sqlite3_context,
+        values: &[*mut sqlite3_value],
+    ) -> Result<()> {
+        let text = values
+            .get(1)
+            .ok_or_else(|| Error::new_message("expected 2nd argument as text"))?;
+        // NULL rows were never added in step()
+        if !api::value_is_null(text) {
+            self.rows.pop_front();
+        }
+        Ok(())
+    }
+}
@@ -1,6 +1,6 @@
 use regex::RegexSet;
 
-use crate::utils::{result_regexset, value_regexset};
+use crate::utils::{result_regexset, value_regex_pointer, value_regexset};
 use sqlite_loadable::prelude::*;
 use sqlite_loadable::{api, Error, Result};
 
@@ -8,8 +8,11 @@ use sqlite_loadable::{api, Error, Result};
 pub fn regexset(context: *mut sqlite3_context, values: &[*mut sqlite3_value]) -> Result<()> {
     let mut patterns = Vec::with_capacity(values.len());
     for value in values {
-        let pattern = api::value_text_notnull(value)?;
-        patterns.push(pattern);
+        if let Some(regex) = value_regex_pointer(value) {
+            patterns.push(unsafe { &*regex }.as_str().to_owned());
+            continue;
+        }
+        patterns.push(api::value_text_notnull(value)?.to_owned());
     }
     let set = RegexSet::new(patterns).map_err(|_| Error::new_message("asdf"))?;
     result_regexset(context, set);
@@ -8,7 +8,10 @@ use sqlite_loadable::{prelude::*, Error};
 
 use std::{mem, os::raw::c_int};
 
-use crate::utils::value_regexset;
+use crate::{
+    ext::{vtab_config, VTabConfig},
+    utils::value_regexset,
+};
 
 static CREATE_SQL: &str = "CREATE TABLE x(key, pattern, regexset hidden, contents hidden)";
 enum Columns {
@@ -38,14 +41,14 @@ impl<'vtab> VTab<'vtab> for RegexSetMatchesTable {
     type Cursor = RegexSetMatchesCursor;
 
     fn connect(
-        _db: *mut sqlite3,
+        db: *mut sqlite3,
         _aux: Option<&Self::Aux>,
         _args: VTabArguments,
     ) -> Result<(String, RegexSetMatchesTable)> {
         let vtab = RegexSetMatchesTable {
             base: unsafe { mem::zeroed() },
         };
-        // TODO db.config(VTabConfig::Innocuous)?;
+        vtab_config(db, VTabConfig::Innocuous)?;
         Ok((CREATE_SQL.to_owned(), vtab))
     }
     fn destroy(&self) -> Result<()> {
@@ -0,0 +1,192 @@
+//! regex_replace_all_fn(), which replaces every match with the result of an
+//! application-defined SQL function. sqlite-loadable's exec::Statement can't
+//! reset or read NULLs, so this prepares and steps the call directly.
+
+use regex::{Captures, Regex};
+use serde_json::Value;
+use sqlite_loadable::ext::sqlite3_stmt;
+use sqlite_loadable::prelude::*;
+use sqlite_loadable::{api, Error, Result};
+
+use std::{
+    ffi::CString,
+    os::raw::{c_int, c_void},
+    ptr, slice,
+};
+
+use crate::ext::{errmsg, sqlite3_api, EXPECT_MESSAGE};
+use crate::utils::{cleanup_regex_value_cached, regex_from_value_or_cache};
+
+/// A prepared `select name(?1, ?2)` or `select name(?1)`, depending on how
+/// many arguments the function takes.
+struct FunctionCall {
+    db: *mut sqlite3,
+    stmt: *mut sqlite3_stmt,
+    takes_captures: bool,
+}
+
+impl FunctionCall {
+    fn prepare(db: *mut sqlite3, name: &str) -> Result<FunctionCall> {
+        let quoted = format!("\"{}\"", name.replace('"', "\"\""));
+        for (sql, takes_captures) in [
+            (format!("select {}(?1, ?2)", quoted), true),
+            (format!("select {}(?1)", quoted), false),
+        ] {
+            let sql = CString::new(sql)?;
+            let mut stmt: *mut sqlite3_stmt = ptr::null_mut();
+            let rc = unsafe {
+                (sqlite3_api().prepare_v2.expect(EXPECT_MESSAGE))(
+                    db,
+                    sql.as_ptr(),
+                    -1,
+                    &mut stmt,
+                    ptr::null_mut(),
+                )
+            };
+            if rc == 0 {
+                return Ok(FunctionCall {
+                    db,
+                    stmt,
+                    takes_captures,
+                });
+            }
+        }
+        Err(Error::new_message(
+            format!(
+                "no SQL function named '{}' that takes 1 or 2 arguments",
+                name
+            )
+            .as_str(),
+        ))
+    }
+
+    fn bind_text(&self, index: c_int, text: &str) {
+        // SQLITE_STATIC is fine, since `text` outlives the step in call()
+        unsafe {
+            (sqlite3_api().bind_text.expect(EXPECT_MESSAGE))(
+                self.stmt,
+                index,
+                text.as_ptr().cast(),
+                text.len() as c_int,
+                None,
+            )
+        };
+    }
+
+    /// Calls the function for one match, returning NULL results as None.
+    fn call(&self, captures: &Captures) -> Result<Option<String>> {
+        let matched = captures.get(0).map_or("", |m| m.as_str());
+        let captures_json;
+        self.bind_text(1, matched);
+        if self.takes_captures {
+            captures_json = Value::Array(
+                captures
+                    .iter()
+                    .map(|group| group.map_or(Value::Null, |m| Value::from(m.as_str())))
+                    .collect(),
+            )
+            .to_string();
+            self.bind_text(2, &captures_json);
+        }
+        unsafe {
+            let rc = (sqlite3_api().step.expect(EXPECT_MESSAGE))(self.stmt);
+            let result = if rc == 100 {
+                // SQLITE_ROW
+                if (sqlite3_api().column_type.expect(EXPECT_MESSAGE))(self.stmt, 0) == 5 {
+                    // SQLITE_NULL
+                    Ok(None)
+                } else {
+                    let text = (sqlite3_api().column_text.expect(EXPECT_MESSAGE))(self.stmt, 0);
+                    let n = (sqlite3_api().column_bytes.expect(EXPECT_MESSAGE))(self.stmt, 0);
+                    Ok(Some(
+                        String::from_utf8_lossy(slice::from_raw_parts(text, n as usize))
+                            .into_owned(),
+                    ))
+                }
+            } else {
+                Err(Error::new_message(errmsg(self.db).as_str()))
+            };
+            (sqlite3_api().reset.expect(EXPECT_MESSAGE))(self.stmt);
+            result
+        }
+    }
+}
+
+impl Drop for FunctionCall {
+    fn drop(&mut self) {
+        unsafe { (sqlite3_api().finalize.expect(EXPECT_MESSAGE))(self.stmt) };
+    }
+}
+
+unsafe extern "C" fn cleanup_function_call(p: *mut c_void) {
+    drop(Box::from_raw(p.cast::<FunctionCall>()))
+}
+
+/// Like regex_from_value_or_cache(), but for the function call: it's prepared
+/// once and cached in auxdata on the function name argument, so it isn't
+/// prepared again for every row. The cache is always owned by SQLite.
+fn function_call_from_cache<'a>(
+    context: *mut sqlite3_context,
+    name: &str,
+    at: usize,
+) -> Result<&'a FunctionCall> {
+    let cached = api::auxdata_get(context, at as i32).cast::<FunctionCall>();
+    if !cached.is_null() {
+        return Ok(unsafe { &*cached });
+    }
+    let call = FunctionCall::prepare(api::context_db_handle(context), name)?;
+    api::auxdata_set(
+        context,
+        at as i32,
+        Box::into_raw(Box::new(call)).cast::<c_void>(),
+        Some(cleanup_function_call),
+    );
+    // SQLite calls the destructor right away if it can't keep it
+    let cached = api::auxdata_get(context, at as i32).cast::<FunctionCall>();
+    if cached.is_null() {
+        return Err(Error::new_message("out of memory"));
+    }
+    Ok(unsafe { &*cached })
+}
+
+fn replace_all_with(call: &FunctionCall, regex: &Regex, content: &str) -> Result<String> {
+    let mut result = String::with_capacity(content.len());
+    let mut last = 0;
+    for captures in regex.captures_iter(content) {
+        let m = captures.get(0).ok_or_else(|| {
+            Error::new_message("sqlite-regex internal error: match without a group 0")
+        })?;
+        result.push_str(&content[last..m.start()]);
+        match call.call(&captures)? {
+            Some(replacement) => result.push_str(&replacement),
+            None => result.push_str(m.as_str()),
+        }
+        last = m.end();
+    }
+    result.push_str(&content[last..]);
+    Ok(result)
+}
+
+/// regex_replace_all_fn(pattern, text, function_name)
+pub fn regex_replace_all_fn(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    let content = api::value_text_notnull(
+        values
+            .get(1)
+            .ok_or_else(|| Error::new_message("expected 2nd argument as contents"))?,
+    )?;
+    let name = api::value_text_notnull(
+        values
+            .get(2)
+            .ok_or_else(|| Error::new_message("expected 3rd argument as function name"))?,
+    )?;
+    let call = function_call_from_cache(context, name, 2)?;
+
+    let (regex, input_type) = regex_from_value_or_cache(context, values, 0)?;
+    let result = replace_all_with(call, unsafe { &*regex }, content);
+    cleanup_regex_value_cached(context, regex, input_type);
+    api::result_text(context, result?)?;
+    Ok(())
+}
@@ -0,0 +1,171 @@
+use regex::{Captures, Regex};
+use serde_json::Value;
+use sqlite_loadable::prelude::*;
+use sqlite_loadable::{api, Error, Result};
+
+use std::{borrow::Cow, collections::HashMap, os::raw::c_void};
+
+use crate::aggregate::Aggregate;
+use crate::utils::{
+    cleanup_regex_value_cached, regex_from_value_or_cache, result_regex_map, value_regex_map,
+};
+
+/// Replacements built by regex_map_agg(), with the keys compiled into one
+/// alternation. `regex` is None when there are no keys.
+pub(crate) struct RegexMap {
+    regex: Option<Regex>,
+    map: HashMap<String, String>,
+}
+
+impl RegexMap {
+    fn new(map: HashMap<String, String>, order: &[String]) -> Result<RegexMap> {
+        if map.is_empty() {
+            return Ok(RegexMap { regex: None, map });
+        }
+        // alternations prefer the first branch that matches, so longer keys
+        // go first to win over their prefixes
+        let mut keys: Vec<&String> = order.iter().collect();
+        keys.sort_by_key(|key| std::cmp::Reverse(key.len()));
+        let pattern = keys
+            .iter()
+            .map(|key| regex::escape(key))
+            .collect::<Vec<String>>()
+            .join("|");
+        let regex = Regex::new(&pattern).map_err(|err| {
+            Error::new_message(format!("Error building regex_map from keys: {}", err).as_str())
+        })?;
+        Ok(RegexMap {
+            regex: Some(regex),
+            map,
+        })
+    }
+}
+
+fn replace_with_map<'t>(
+    regex: &Regex,
+    text: &'t str,
+    map: &HashMap<String, String>,
+) -> Cow<'t, str> {
+    regex.replace_all(text, |captures: &Captures| {
+        let matched = captures.get(0).map_or("", |m| m.as_str());
+        map.get(matched)
+            .cloned()
+            .unwrap_or_else(|| matched.to_owned())
+    })
+}
+
+/// Parses a JSON object into replacements. Strings are used as-is, `null`
+/// values are skipped, and any other value is used as its JSON text.
+fn parse_json_map(json: &str) -> Result<HashMap<String, String>> {
+    let object = match serde_json::from_str::<Value>(json) {
+        Ok(Value::Object(object)) => object,
+        _ => return Err(Error::new_message("expected json_map to be a JSON object")),
+    };
+    Ok(object
+        .into_iter()
+        .filter_map(|(key, value)| match value {
+            Value::Null => None,
+            Value::String(value) => Some((key, value)),
+            value => Some((key, value.to_string())),
+        })
+        .collect())
+}
+
+unsafe extern "C" fn cleanup_json_map(p: *mut c_void) {
+    drop(Box::from_raw(p.cast::<HashMap<String, String>>()))
+}
+
+/// regex_replace_map(pattern, text, json_map)
+/// regex_replace_map(regex_map, text)
+pub fn regex_replace_map(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+) -> Result<()> {
+    let text = api::value_text_notnull(
+        values
+            .get(1)
+            .ok_or_else(|| Error::new_message("expected 2nd argument as text"))?,
+    )?;
+
+    let json_map = match values.get(2) {
+        Some(json_map) => json_map,
+        None => {
+            let map = value_regex_map(
+                values
+                    .first()
+                    .ok_or_else(|| Error::new_message("expected 1st argument as regex_map"))?,
+            )?;
+            let map = unsafe { &*map };
+            match &map.regex {
+                Some(regex) => api::result_text(context, replace_with_map(regex, text, &map.map))?,
+                None => api::result_text(context, text)?,
+            }
+            return Ok(());
+        }
+    };
+
+    // the parsed JSON object is cached like the pattern, for when the same
+    // map is used across many rows
+    let mut map = api::auxdata_get(context, 2).cast::<HashMap<String, String>>();
+    let cached = !map.is_null();
+    if !cached {
+        map = Box::into_raw(Box::new(parse_json_map(api::value_text_notnull(
+            json_map,
+        )?)?));
+    }
+    let result = regex_from_value_or_cache(context, values, 0).and_then(|(regex, input_type)| {
+        let result = api::result_text(
+            context,
+            replace_with_map(unsafe { &*regex }, text, unsafe { &*map }),
+        );
+        cleanup_regex_value_cached(context, regex, input_type);
+        result
+    });
+    if !cached {
+        api::auxdata_set(context, 2, map.cast::<c_void>(), Some(cleanup_json_map));
+    }
+    result
+}
+
+/// regex_map_agg(key, value)
+#[derive(Default)]
+pub(crate) struct RegexMapAgg {
+    map: HashMap<String, String>,
+    order: Vec<String>,
+}
+
+impl Aggregate for RegexMapAgg {
+    fn step(
+        &mut self,
+        _context: *mut sqlite3_context,
+        values: &[*mut sqlite3_value],
+    ) -> Result<()> {
+        let key = values
+            .first()
+            .ok_or_else(|| Error::new_message("expected 1st argument as key"))?;
+        let value = values
+            .get(1)
+            .ok_or_else(|| Error::new_message("expected 2nd argument as value"))?;
+        if api::value_is_null(key) || api::value_is_null(value) {
+            return Ok(());
+        }
+        let key = api::value_text(key)?;
+        if key.is_empty() {
+            return Ok(());
+        }
+        // later rows replace earlier ones with the same key
+        if self
+            .map
+            .insert(key.to_owned(), api::value_text(value)?.to_owned())
+            .is_none()
+        {
+            self.order.push(key.to_owned());
+        }
+        Ok(())
+    }
+
+    fn result(&self, context: *mut sqlite3_context) -> Result<()> {
+        result_regex_map(context, RegexMap::new(self.map.clone(), &self.order)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,298 @@
+//! Extended replacement templates, for regex_replace(..., 'ext') and
+//! regex_replace_all(..., 'ext'). On top of the usual `$1`/`${name}`
+//! expansion, these support Perl/sed-style case conversion with `\U`, `\L`,
+//! `\E`, `\u` and `\l`, and conditional text with `${name:+text}` and
+//! `${name:-text}`.
+
+use regex::{Captures, Replacer};
+use sqlite_loadable::prelude::*;
+use sqlite_loadable::{api, Error, Result};
+use std::os::raw::c_void;
+
+enum Group {
+    Index(usize),
+    Name(String),
+}
+
+impl Group {
+    fn parse(group: &str) -> Group {
+        match group.parse::<usize>() {
+            Ok(index) => Group::Index(index),
+            Err(_) => Group::Name(group.to_owned()),
+        }
+    }
+
+    fn get<'t>(&self, captures: &Captures<'t>) -> Option<&'t str> {
+        match self {
+            Group::Index(index) => captures.get(*index),
+            Group::Name(name) => captures.name(name),
+        }
+        .map(|m| m.as_str())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Case {
+    Upper,
+    Lower,
+}
+
+enum Piece {
+    Literal(String),
+    Group(Group),
+    /// `${group:+text}`, text only if the group participated.
+    IfSet(Group, Vec<Piece>),
+    /// `${group:-text}`, the group, or text if it didn't participate.
+    IfUnset(Group, Vec<Piece>),
+    /// `\U`, `\L` or `\E` (None).
+    SetCase(Option<Case>),
+    /// `\u` or `\l`.
+    NextCase(Case),
+}
+
+pub(crate) struct Template {
+    pieces: Vec<Piece>,
+}
+
+fn is_group_char(c: char) -> bool {
+    c == '_' || c.is_ascii_alphanumeric()
+}
+
+struct Parser<'a> {
+    template: &'a str,
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    /// Parses pieces until the end of the template, or until an unescaped
+    /// `}` if `nested` is true.
+    fn parse(&mut self, nested: bool) -> Result<Vec<Piece>> {
+        let mut pieces = vec![];
+        let mut literal = String::new();
+        while let Some(c) = self.chars.next() {
+            let piece = match c {
+                '}' if nested => {
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Literal(literal));
+                    }
+                    return Ok(pieces);
+                }
+                '\\' => match self.chars.next() {
+                    Some('U') => Piece::SetCase(Some(Case::Upper)),
+                    Some('L') => Piece::SetCase(Some(Case::Lower)),
+                    Some('E') => Piece::SetCase(None),
+                    Some('u') => Piece::NextCase(Case::Upper),
+                    Some('l') => Piece::NextCase(Case::Lower),
+                    Some('n') => {
+                        literal.push('\n');
+                        continue;
+                    }
+                    Some('t') => {
+                        literal.push('\t');
+                        continue;
+                    }
+                    Some(c @ ('\\' | '$' | '}')) => {
+                        literal.push(c);
+                        continue;
+                    }
+                    Some(c) => {
+                        return Err(Error::new_message(
+                            format!(
+                                "unknown escape '\\{}' in replacement, expected one of \\U \\L \\E \\u \\l \\n \\t \\\\ \\$ \\}}",
+                                c
+                            )
+                            .as_str(),
+                        ))
+                    }
+                    None => {
+                        return Err(Error::new_message(
+                            "replacement can't end with a single '\\'",
+                        ))
+                    }
+                },
+                '$' => match self.chars.peek().copied() {
+                    Some('$') => {
+                        self.chars.next();
+                        literal.push('$');
+                        continue;
+                    }
+                    Some('{') => {
+                        self.chars.next();
+                        self.parse_braced()?
+                    }
+                    Some(c) if is_group_char(c) => Piece::Group(Group::parse(&self.group_name())),
+                    _ => {
+                        literal.push('$');
+                        continue;
+                    }
+                },
+                c => {
+                    literal.push(c);
+                    continue;
+                }
+            };
+            if !literal.is_empty() {
+                pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+            }
+            pieces.push(piece);
+        }
+        if nested {
+            return Err(Error::new_message(
+                format!("unclosed '${{' in replacement '{}'", self.template).as_str(),
+            ));
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+        Ok(pieces)
+    }
+
+    fn group_name(&mut self) -> String {
+        let mut name = String::new();
+        while let Some(c) = self.chars.next_if(|c| is_group_char(*c)) {
+            name.push(c);
+        }
+        name
+    }
+
+    /// Parses the rest of `${group}`, `${group:+text}` or `${group:-text}`.
+    fn parse_braced(&mut self) -> Result<Piece> {
+        let name = self.group_name();
+        if name.is_empty() {
+            return Err(Error::new_message(
+                format!(
+                    "expected a group name after '${{' in replacement '{}'",
+                    self.template
+                )
+                .as_str(),
+            ));
+        }
+        let group = Group::parse(&name);
+        match self.chars.next() {
+            Some('}') => Ok(Piece::Group(group)),
+            Some(':') => match self.chars.next() {
+                Some('+') => Ok(Piece::IfSet(group, self.parse(true)?)),
+                Some('-') => Ok(Piece::IfUnset(group, self.parse(true)?)),
+                _ => Err(Error::new_message(
+                    format!(
+                        "expected ':+' or ':-' after '${{{}' in replacement '{}'",
+                        name, self.template
+                    )
+                    .as_str(),
+                )),
+            },
+            _ => Err(Error::new_message(
+                format!("unclosed '${{' in replacement '{}'", self.template).as_str(),
+            )),
+        }
+    }
+}
+
+/// Whether the optional `mode` argument asks for extended templates. NULL or
+/// an empty string means the usual `$1`/`${name}` expansion.
+pub(crate) fn value_extended_mode(value: Option<&*mut sqlite3_value>) -> Result<bool> {
+    let value = match value {
+        Some(value) if !api::value_is_null(value) => value,
+        _ => return Ok(false),
+    };
+    match api::value_text(value)? {
+        "" => Ok(false),
+        "ext" => Ok(true),
+        mode => Err(Error::new_message(
+            format!("unknown replacement mode '{}', expected 'ext'", mode).as_str(),
+        )),
+    }
+}
+
+impl Template {
+    pub(crate) fn parse(template: &str) -> Result<Template> {
+        let mut parser = Parser {
+            template,
+            chars: template.chars().peekable(),
+        };
+        Ok(Template {
+            pieces: parser.parse(false)?,
+        })
+    }
+}
+
+unsafe extern "C" fn cleanup_template(p: *mut c_void) {
+    drop(Box::from_raw(p.cast::<Template>()))
+}
+
+/// Like regex_from_value_or_cache(), but for the template: it's parsed once
+/// and cached in auxdata on the replacement argument, so a constant template
+/// isn't parsed again for every row. The cache is always owned by SQLite.
+pub(crate) fn template_from_cache<'a>(
+    context: *mut sqlite3_context,
+    template: &str,
+    at: usize,
+) -> Result<&'a Template> {
+    let cached = api::auxdata_get(context, at as i32).cast::<Template>();
+    if !cached.is_null() {
+        return Ok(unsafe { &*cached });
+    }
+    let parsed = Template::parse(template)?;
+    api::auxdata_set(
+        context,
+        at as i32,
+        Box::into_raw(Box::new(parsed)).cast::<c_void>(),
+        Some(cleanup_template),
+    );
+    // SQLite calls the destructor right away if it can't keep it
+    let cached = api::auxdata_get(context, at as i32).cast::<Template>();
+    if cached.is_null() {
+        return Err(Error::new_message("out of memory"));
+    }
+    Ok(unsafe { &*cached })
+}
+
+/// Output that applies the current case conversion to everything written.
+struct Output<'a> {
+    dst: &'a mut String,
+    case: Option<Case>,
+    next: Option<Case>,
+}
+
+impl Output<'_> {
+    fn push_str(&mut self, s: &str) {
+        for c in s.chars() {
+            match self.next.take().or(self.case) {
+                Some(Case::Upper) => self.dst.extend(c.to_uppercase()),
+                Some(Case::Lower) => self.dst.extend(c.to_lowercase()),
+                None => self.dst.push(c),
+            }
+        }
+    }
+
+    fn expand(&mut self, pieces: &[Piece], captures: &Captures) {
+        for piece in pieces {
+            match piece {
+                Piece::Literal(literal) => self.push_str(literal),
+                Piece::Group(group) => self.push_str(group.get(captures).unwrap_or("")),
+                Piece::IfSet(group, pieces) => {
+                    if group.get(captures).is_some() {
+                        self.expand(pieces, captures);
+                    }
+                }
+                Piece::IfUnset(group, pieces) => match group.get(captures) {
+                    Some(value) => self.push_str(value),
+                    None => self.expand(pieces, captures),
+                },
+                Piece::SetCase(case) => self.case = *case,
+                Piece::NextCase(case) => self.next = Some(*case),
+            }
+        }
+    }
+}
+
+impl Replacer for &Template {
+    fn replace_append(&mut self, captures: &Captures<'_>, dst: &mut String) {
+        Output {
+            dst,
+            case: None,
+            next: None,
+        }
+        .expand(&self.pieces, captures);
+    }
+}
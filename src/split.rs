@@ -7,7 +7,10 @@ use sqlite_loadable::{prelude::*, Error};
 
 use std::{marker::PhantomData, mem, os::raw::c_int};
 
-use crate::utils::value_regex;
+use crate::{
+    ext::{vtab_config, VTabConfig},
+    utils::value_regex,
+};
 
 static CREATE_SQL: &str = "CREATE TABLE x(item text, pattern hidden, contents text hidden)";
 enum Columns {
@@ -35,13 +38,13 @@ impl<'vtab> VTab<'vtab> for RegexSplitTable {
     type Cursor = RegexSplitCursor<'vtab>;
 
     fn connect(
-        _db: *mut sqlite3,
+        db: *mut sqlite3,
         _aux: Option<&()>,
         _args: VTabArguments,
     ) -> Result<(String, RegexSplitTable)> {
         let base: sqlite3_vtab = unsafe { mem::zeroed() };
         let vtab = RegexSplitTable { base };
-        // TODO db.config(VTabConfig::Innocuous)?;
+        vtab_config(db, VTabConfig::Innocuous)?;
         Ok((CREATE_SQL.to_owned(), vtab))
     }
     fn destroy(&self) -> Result<()> {
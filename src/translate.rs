@@ -0,0 +1,87 @@
+use regex::Regex;
+use sqlite_loadable::prelude::*;
+use sqlite_loadable::{api, Error, Result};
+
+use crate::utils::{glob_to_pattern, result_regex};
+
+/// Translates a SQLite LIKE pattern into an equivalent, anchored regex pattern.
+/// `%` matches any sequence of characters and `_` matches exactly one character.
+/// Like SQLite's LIKE, only ASCII letters are compared case-insensitively, so
+/// they're translated into classes like `[aA]` instead of using the `i` flag,
+/// which would also fold non-ASCII characters.
+pub(crate) fn like_to_pattern(like: &str, escape: Option<char>) -> Result<String> {
+    let mut pattern = String::from("(?s)^");
+    let mut chars = like.chars();
+    while let Some(c) = chars.next() {
+        let c = if Some(c) == escape {
+            chars.next().ok_or_else(|| {
+                Error::new_message("LIKE pattern can't end with the escape character")
+            })?
+        } else {
+            match c {
+                '%' => {
+                    pattern.push_str(".*");
+                    continue;
+                }
+                '_' => {
+                    pattern.push('.');
+                    continue;
+                }
+                c => c,
+            }
+        };
+        if c.is_ascii_alphabetic() {
+            pattern.push('[');
+            pattern.push(c.to_ascii_lowercase());
+            pattern.push(c.to_ascii_uppercase());
+            pattern.push(']');
+        } else {
+            pattern.push_str(&regex::escape(&c.to_string()));
+        }
+    }
+    pattern.push('$');
+    Ok(pattern)
+}
+
+/// regex_from_like(pattern [, escape])
+pub fn regex_from_like(context: *mut sqlite3_context, values: &[*mut sqlite3_value]) -> Result<()> {
+    let like = api::value_text_notnull(
+        values
+            .first()
+            .ok_or_else(|| Error::new_message("expected 1st argument as pattern"))?,
+    )?;
+    let escape = match values.get(1) {
+        Some(value) => {
+            let escape = api::value_text_notnull(value)?;
+            let mut chars = escape.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(c),
+                _ => {
+                    return Err(Error::new_message(
+                        "ESCAPE expression must be a single character",
+                    ))
+                }
+            }
+        }
+        None => None,
+    };
+    let regex = Regex::new(&like_to_pattern(like, escape)?).map_err(|err| {
+        Error::new_message(format!("Error translating LIKE pattern: {}", err).as_str())
+    })?;
+    result_regex(context, regex);
+    Ok(())
+}
+
+/// regex_from_glob(pattern)
+pub fn regex_from_glob(context: *mut sqlite3_context, values: &[*mut sqlite3_value]) -> Result<()> {
+    let glob = api::value_text_notnull(
+        values
+            .first()
+            .ok_or_else(|| Error::new_message("expected 1st argument as pattern"))?,
+    )?;
+    let regex = Regex::new(&glob_to_pattern(glob)).map_err(|err| {
+        Error::new_message(format!("Error translating GLOB pattern: {}", err).as_str())
+    })?;
+    result_regex(context, regex);
+    Ok(())
+}
@@ -1,16 +1,33 @@
-use regex::{Captures, Regex, RegexSet};
+use regex::{Captures, Match, Regex, RegexSet};
 use sqlite_loadable::prelude::*;
 use sqlite_loadable::{api, Error, Result};
 use std::os::raw::c_void;
 
+use crate::acset::AcSet;
+use crate::functions::{anchored_pattern, Group};
+use crate::replace_map::RegexMap;
+
 // Raw bytes as performance. the string MUST end in the null byte '\0'
 const REGEX_POINTER_NAME: &[u8] = b"regex0\0";
 
+/// Returns the regex if `value` is a regex object from regex(), without
+/// falling back to compiling text patterns.
+pub(crate) fn value_regex_pointer(value: &*mut sqlite3_value) -> Option<*mut Regex> {
+    unsafe { api::value_pointer(value, REGEX_POINTER_NAME) }
+}
+
+/// Returns the pattern of a regex object from regex(), or a text pattern as
+/// is, without compiling it.
+pub(crate) fn value_pattern<'a>(value: &*mut sqlite3_value) -> Result<&'a str> {
+    match value_regex_pointer(value) {
+        Some(regex) => Ok(unsafe { &*regex }.as_str()),
+        None => api::value_text_notnull(value),
+    }
+}
+
 pub fn value_regex(value: &*mut sqlite3_value) -> Result<*mut Regex> {
-    unsafe {
-        if let Some(regex) = api::value_pointer(value, REGEX_POINTER_NAME) {
-            return Ok(regex);
-        }
+    if let Some(regex) = value_regex_pointer(value) {
+        return Ok(regex);
     }
     let pattern = api::value_text_notnull(value)?;
     let x = Box::new(
@@ -20,18 +37,33 @@ pub fn value_regex(value: &*mut sqlite3_value) -> Result<*mut Regex> {
     Ok(Box::into_raw(x))
 }
 
+/// Like value_regex(), but returns an owned regex, for callers that keep it
+/// around longer than a single function call.
+pub(crate) fn value_regex_owned(value: &*mut sqlite3_value) -> Result<Regex> {
+    if let Some(regex) = value_regex_pointer(value) {
+        return Ok(unsafe { &*regex }.clone());
+    }
+    Ok(*unsafe { Box::from_raw(value_regex(value)?) })
+}
+
 pub fn result_regex(context: *mut sqlite3_context, regex: Regex) {
     api::result_pointer(context, REGEX_POINTER_NAME, regex)
 }
 
+#[derive(Clone)]
 pub(crate) enum CaptureGroupKey {
     Index(usize),
     Name(String),
 }
 
+#[derive(Clone)]
 pub(crate) struct CaptureGroup {
     pub key: CaptureGroupKey,
     pub value: Option<String>,
+    /// The byte and character offsets of `value` in the text, or 0 when the
+    /// group didn't participate in the match.
+    pub start: usize,
+    pub char_start: usize,
 }
 const REGEX_CAPTURES_NAME: &[u8] = b"regex_captures0\0";
 
@@ -44,21 +76,75 @@ pub(crate) fn value_regex_captures(value: &*mut sqlite3_value) -> Result<*mut Ve
     Err(Error::new_message("value is not a regex captures object"))
 }
 
-pub fn result_regex_captures(context: *mut sqlite3_context, regex: &Regex, captures: &Captures) {
+/// Finds `group` in a captures object. Group 0 is the entire match.
+pub(crate) fn find_capture_group<'a>(
+    captures: &'a [CaptureGroup],
+    group: Group,
+) -> Option<&'a CaptureGroup> {
+    captures.iter().find(|c| match (&c.key, group) {
+        (CaptureGroupKey::Index(index), Group::Index(lookup)) => *index == lookup,
+        (CaptureGroupKey::Name(name), Group::Name(lookup)) => name == lookup,
+        _ => false,
+    })
+}
+
+/// A group argument: integers are group indexes, anything else a group name.
+pub(crate) fn value_group(value: &*mut sqlite3_value) -> Result<Group<'_>> {
+    match api::value_type(value) {
+        api::ValueType::Integer => Ok(Group::Index(api::value_int64(value) as usize)),
+        _ => Ok(Group::Name(api::value_text(value)?)),
+    }
+}
+
+/// `char_start` is the character offset of the entire match in the text, so
+/// the offsets of the groups don't need the text before it.
+pub(crate) fn capture_groups(
+    regex: &Regex,
+    captures: &Captures,
+    char_start: usize,
+) -> Vec<CaptureGroup> {
+    let whole = captures.get(0);
+    let group = |key: CaptureGroupKey, m: Option<Match>| match m {
+        Some(m) => CaptureGroup {
+            key,
+            value: Some(m.as_str().to_string()),
+            start: m.start(),
+            char_start: char_start
+                + whole.map_or(0, |whole| {
+                    whole.as_str()[..m.start() - whole.start()].chars().count()
+                }),
+        },
+        None => CaptureGroup {
+            key,
+            value: None,
+            start: 0,
+            char_start: 0,
+        },
+    };
     let mut caps: Vec<CaptureGroup> = vec![];
     for name in regex.capture_names().flatten() {
-        caps.push(CaptureGroup {
-            key: CaptureGroupKey::Name(name.to_string()),
-            value: captures.name(name).map(|v| v.as_str().to_string()),
-        })
+        caps.push(group(
+            CaptureGroupKey::Name(name.to_string()),
+            captures.name(name),
+        ))
     }
     for (i, m) in captures.iter().enumerate() {
-        caps.push(CaptureGroup {
-            key: CaptureGroupKey::Index(i),
-            value: m.map(|v| v.as_str().to_string()),
-        })
+        caps.push(group(CaptureGroupKey::Index(i), m))
     }
-    api::result_pointer(context, REGEX_CAPTURES_NAME, caps)
+    caps
+}
+
+pub(crate) fn result_capture_groups(context: *mut sqlite3_context, groups: Vec<CaptureGroup>) {
+    api::result_pointer(context, REGEX_CAPTURES_NAME, groups)
+}
+
+pub fn result_regex_captures(
+    context: *mut sqlite3_context,
+    regex: &Regex,
+    captures: &Captures,
+    char_start: usize,
+) {
+    result_capture_groups(context, capture_groups(regex, captures, char_start))
 }
 
 pub enum RegexInputType {
@@ -101,7 +187,7 @@ pub fn regex_from_value_or_cache(
     }
 }
 
-unsafe extern "C" fn cleanup_regex(arg1: *mut c_void) {
+pub(crate) unsafe extern "C" fn cleanup_regex(arg1: *mut c_void) {
     drop(Box::from_raw(arg1.cast::<Regex>()))
 }
 
@@ -125,6 +211,59 @@ pub fn cleanup_regex_value_cached(
 // Raw bytes as performance. the string MUST end in the null byte '\0'
 const REGEX_SET_POINTER_NAME: &[u8] = b"regexset0\0";
 
+/// A regex compiled with anchored_pattern(), and the pattern it came from.
+struct AnchoredRegex {
+    source: String,
+    regex: Regex,
+}
+
+unsafe extern "C" fn cleanup_anchored_regex(p: *mut c_void) {
+    drop(Box::from_raw(p.cast::<AnchoredRegex>()))
+}
+
+/// Like regex_from_value_or_cache(), but returns the anchored form of the
+/// pattern, for regex_full_match(). The anchored form is cached in auxdata
+/// separately from the pattern itself, which includes regex objects from
+/// regex(), so they aren't recompiled every row either. The cache is always
+/// owned by SQLite, so there's nothing to clean up.
+pub(crate) fn anchored_regex_from_value_or_cache(
+    context: *mut sqlite3_context,
+    values: &[*mut sqlite3_value],
+    at: usize,
+) -> Result<*const Regex> {
+    let value = values
+        .get(at)
+        .ok_or_else(|| Error::new_message("expected 1st argument as pattern"))?;
+    let source = match value_regex_pointer(value) {
+        Some(regex) => unsafe { &*regex }.as_str(),
+        None => api::value_text_notnull(value)?,
+    };
+
+    let cached = api::auxdata_get(context, at as i32).cast::<AnchoredRegex>();
+    if !cached.is_null() && unsafe { &(*cached).source } == source {
+        return Ok(unsafe { &(*cached).regex });
+    }
+
+    let regex = Regex::new(&anchored_pattern(source))
+        .map_err(|_| Error::new_message("pattern not valid regex"))?;
+    let anchored = Box::into_raw(Box::new(AnchoredRegex {
+        source: source.to_owned(),
+        regex,
+    }));
+    api::auxdata_set(
+        context,
+        at as i32,
+        anchored.cast::<c_void>(),
+        Some(cleanup_anchored_regex),
+    );
+    // SQLite calls the destructor right away if it can't keep it
+    let anchored = api::auxdata_get(context, at as i32).cast::<AnchoredRegex>();
+    if anchored.is_null() {
+        return Err(Error::new_message("out of memory"));
+    }
+    Ok(unsafe { &(*anchored).regex })
+}
+
 pub fn value_regexset(value: &*mut sqlite3_value) -> Result<*mut RegexSet> {
     unsafe {
         if let Some(regex) = api::value_pointer(value, REGEX_SET_POINTER_NAME) {
@@ -137,3 +276,95 @@ pub fn value_regexset(value: &*mut sqlite3_value) -> Result<*mut RegexSet> {
 pub fn result_regexset(context: *mut sqlite3_context, set: RegexSet) {
     api::result_pointer(context, REGEX_SET_POINTER_NAME, set)
 }
+
+// Raw bytes as performance. the string MUST end in the null byte '\0'
+const AC_SET_POINTER_NAME: &[u8] = b"ac_set0\0";
+
+pub(crate) fn value_ac_set(value: &*mut sqlite3_value) -> Result<*mut AcSet> {
+    unsafe {
+        if let Some(set) = api::value_pointer(value, AC_SET_POINTER_NAME) {
+            return Ok(set);
+        }
+    }
+    Err(Error::new_message("value is not an ac_set object"))
+}
+
+pub(crate) fn result_ac_set(context: *mut sqlite3_context, set: AcSet) {
+    api::result_pointer(context, AC_SET_POINTER_NAME, set)
+}
+
+// Raw bytes as performance. the string MUST end in the null byte '\0'
+const REGEX_MAP_POINTER_NAME: &[u8] = b"regex_map0\0";
+
+pub(crate) fn value_regex_map(value: &*mut sqlite3_value) -> Result<*mut RegexMap> {
+    unsafe {
+        if let Some(map) = api::value_pointer(value, REGEX_MAP_POINTER_NAME) {
+            return Ok(map);
+        }
+    }
+    Err(Error::new_message(
+        "value is not a regex_map object, expected the result of regex_map_agg()",
+    ))
+}
+
+pub(crate) fn result_regex_map(context: *mut sqlite3_context, map: RegexMap) {
+    api::result_pointer(context, REGEX_MAP_POINTER_NAME, map)
+}
+
+/// Translates a SQLite GLOB pattern into an equivalent, anchored regex pattern.
+/// `*` matches any sequence of characters, `?` matches exactly one character,
+/// and `[...]` / `[^...]` are character classes. Like GLOB, matching is
+/// case-sensitive, `*` also matches across `/`, and a pattern with an
+/// unterminated `[` never matches.
+pub(crate) fn glob_to_pattern(glob: &str) -> String {
+    let chars: Vec<char> = glob.chars().collect();
+    let mut pattern = String::from("(?s)^");
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            '[' => {
+                let mut j = i + 1;
+                let negated = chars.get(j) == Some(&'^');
+                if negated {
+                    j += 1;
+                }
+                let start = j;
+                // a ']' directly after '[' or '[^' is a literal member of the class
+                if chars.get(j) == Some(&']') {
+                    j += 1;
+                }
+                while j < chars.len() && chars[j] != ']' {
+                    j += 1;
+                }
+                if j >= chars.len() {
+                    // like GLOB, a pattern with an unterminated class never matches
+                    return String::from(r"\b\B");
+                } else {
+                    pattern.push('[');
+                    if negated {
+                        pattern.push('^');
+                    }
+                    let mut k = start;
+                    while k < j {
+                        pattern.push_str(&regex::escape(&chars[k].to_string()));
+                        if k + 2 < j && chars[k + 1] == '-' {
+                            pattern.push('-');
+                            pattern.push_str(&regex::escape(&chars[k + 2].to_string()));
+                            k += 3;
+                        } else {
+                            k += 1;
+                        }
+                    }
+                    pattern.push(']');
+                    i = j;
+                }
+            }
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    pattern.push('$');
+    pattern
+}
@@ -2,6 +2,8 @@ import sqlite3
 import unittest
 import time
 import os
+import tempfile
+import json
 
 EXT_PATH="./dist/debug/regex0"
 
@@ -32,15 +34,66 @@ def execute_all(sql, args=None):
   return list(map(lambda x: dict(x), results))
 
 FUNCTIONS = [
+  "ac_is_match",
+  "ac_set",
+  "ac_set_agg",
+  "ac_set_agg",
+  "ac_set_print",
+  "ac_set_with_options",
   "regex",
+  "regex",
+  "regex_ast",
+  "regex_builtin",
   "regex_capture",
   "regex_capture",
+  "regex_capture_end",
+  "regex_capture_end",
+  "regex_capture_end",
+  "regex_capture_nth",
+  "regex_capture_span",
+  "regex_capture_span",
+  "regex_capture_span",
+  "regex_capture_start",
+  "regex_capture_start",
+  "regex_capture_start",
+  "regex_captures_len",
+  "regex_compose",
+  "regex_count_agg",
+  "regex_create_collation",
   "regex_debug",
+  "regex_define",
+  "regex_define",
+  "regex_extract_all",
+  "regex_extract_all",
   "regex_find",
   "regex_find_at",
+  "regex_find_nth",
+  "regex_find_window",
+  "regex_find_window",
+  "regex_from_glob",
+  "regex_from_like",
+  "regex_from_like",
+  "regex_full_match",
+  "regex_group_index",
+  "regex_hir",
+  "regex_literals",
+  "regex_map_agg",
+  "regex_matches_agg",
+  "regex_named",
   "regex_print",
+  "regex_redact",
+  "regex_redact",
+  "regex_replace",
   "regex_replace",
   "regex_replace_all",
+  "regex_replace_all",
+  "regex_replace_all_fn",
+  "regex_replace_map",
+  "regex_replace_map",
+  "regex_undefine",
+  "regex_union_agg",
+  "regex_union_agg",
+  "regex_union_agg",
   "regex_valid",
   "regex_version",
   "regexp",
@@ -50,8 +103,13 @@ FUNCTIONS = [
 ]
 
 MODULES = [
+  "ac_find_all",
+  "regex_builtins",
+  "regex_capture_names",
   "regex_captures",
   "regex_find_all",
+  "regex_grep",
+  "regex_read_lines",
   "regex_split",
   "regexset_matches",
 ]
@@ -67,6 +125,74 @@ class TestRegex(unittest.TestCase):
     modules = list(map(lambda a: a[0], db.execute("select name from loaded_modules").fetchall()))
     self.assertEqual(modules, MODULES)
 
+  def test_trusted_schema(self):
+    tdb = connect(EXT_PATH)
+    tdb.execute("pragma trusted_schema = off")
+    tdb.execute("""
+      create table users(
+        email text check (email regexp '^\\S+@\\S+$'),
+        domain text generated always as (regex_capture('@(.+)$', email, 1))
+      )
+    """)
+    tdb.execute("insert into users(email) values ('alex@example.com')")
+    self.assertEqual(tdb.execute("select domain from users").fetchone()[0], 'example.com')
+    with self.assertRaisesRegex(sqlite3.IntegrityError, "CHECK constraint failed"):
+      tdb.execute("insert into users(email) values ('not an email')")
+
+    tdb.execute("create view digits as select match from regex_find_all('\\d', 'a1b2')")
+    self.assertEqual([row[0] for row in tdb.execute("select * from digits")], ['1', '2'])
+
+    # '@name' patterns aren't looked up in regex_patterns, so they're still pure
+    tdb.execute("create view mentions as select regexp('@admin', 'hi @admin') as mentioned")
+    self.assertEqual(tdb.execute("select mentioned from mentions").fetchone()[0], 1)
+
+    # regex_named() reads from regex_patterns, so it isn't innocuous
+    tdb.execute("create view named as select regex_named('ticket')")
+    with self.assertRaisesRegex(sqlite3.OperationalError, "unsafe use of regex_named()"):
+      tdb.execute("select * from named").fetchall()
+
+    # regex_debug() exposes build details, so it isn't innocuous
+    tdb.execute("create view debug as select regex_debug()")
+    with self.assertRaisesRegex(sqlite3.OperationalError, "unsafe use of regex_debug()"):
+      tdb.execute("select * from debug").fetchall()
+    tdb.execute("create view version as select regex_version()")
+    self.assertTrue(tdb.execute("select * from version").fetchone()[0].startswith('v'))
+
+    # filesystem table functions can't be used in schemas
+    tdb.execute("create view lines as select * from regex_read_lines('README.md', 'x')")
+    with self.assertRaisesRegex(sqlite3.OperationalError, "unsafe use of virtual table"):
+      tdb.execute("select * from lines").fetchall()
+    tdb.close()
+
+  def test_entrypoints(self):
+    def loaded(entrypoint):
+      edb = sqlite3.connect(":memory:")
+      base_functions = set(row[0] for row in edb.execute("select name from pragma_function_list"))
+      base_modules = set(row[0] for row in edb.execute("select name from pragma_module_list"))
+      edb.enable_load_extension(True)
+      edb.execute("select load_extension(?, ?)", [EXT_PATH, entrypoint])
+      functions = set(row[0] for row in edb.execute("select name from pragma_function_list")) - base_functions
+      modules = set(row[0] for row in edb.execute("select name from pragma_module_list")) - base_modules
+      edb.close()
+      return functions, modules
+
+    all_functions, all_modules = set(FUNCTIONS), set(MODULES)
+    # functions that touch the database or the connection are only in sqlite3_regex_init
+    pure_functions = all_functions - {
+      "regex_define",
+      "regex_undefine",
+      "regex_named",
+      "regex_create_collation",
+      "regex_replace_all_fn",
+    }
+    self.assertEqual(loaded("sqlite3_regex_init"), (all_functions, all_modules))
+    self.assertEqual(loaded("sqlite3_regex_scalar_init"), (pure_functions, set()))
+    self.assertEqual(loaded("sqlite3_regex_tables_init"), (set(), all_modules))
+    self.assertEqual(
+      loaded("sqlite3_regex_safe_init"),
+      (pure_functions, all_modules - {"regex_read_lines", "regex_grep"})
+    )
+
   def test_regex_version(self):
     self.assertEqual(db.execute("select regex_version()").fetchone()[0][0], "v")
 
@@ -81,11 +207,238 @@ class TestRegex(unittest.TestCase):
     with self.assertRaisesRegex(sqlite3.OperationalError, "Error parsing pattern as regex: regex parse error:.*"):
       regex("[nope")
 
+    regex_flags = lambda pattern, flags: db.execute("select regex_print(regex(?, ?))", [pattern, flags]).fetchone()[0]
+    self.assertEqual(regex_flags('abc', 'i'), '(?i)abc')
+    self.assertEqual(regex_flags('abc', ''), 'abc')
+    self.assertEqual(db.execute("select regex_find(regex('a.c', 'is'), 'xA\nCx')").fetchone()[0], 'A\nC')
+    with self.assertRaisesRegex(sqlite3.OperationalError, "unknown regex flag 'q'"):
+      regex_flags('abc', 'q')
+
+    # 'A' anchors the pattern instead of being an inline flag
+    self.assertEqual(regex_flags('a|ab', 'A'), r'\A(?:a|ab)\z')
+    self.assertEqual(regex_flags('abc', 'iA'), r'\A(?:(?i)abc)\z')
+    self.assertEqual(db.execute("select regexp(regex('a|ab', 'A'), 'ab')").fetchone()[0], 1)
+    self.assertEqual(db.execute("select regexp(regex('a|ab', 'A'), 'abc')").fetchone()[0], 0)
+    self.assertEqual(db.execute("select regexp(regex('ABC', 'Ai'), 'abc')").fetchone()[0], 1)
+    with self.assertRaisesRegex(sqlite3.OperationalError, "Error parsing pattern as regex: regex parse error:"):
+      regex_flags('[nope', 'A')
+
+
+  def test_regex_define(self):
+    regex_define = lambda *args: db.execute("select regex_define({args})".format(args=spread_args(args)), args).fetchone()[0]
+    self.assertEqual(regex_define('ticket', '[A-Z]+-\\d+'), 'ticket')
+    self.assertEqual(regex_define('@word', 'hello', 'i'), 'word')
+    self.assertEqual(
+      execute_all("select name, pattern, flags from regex_patterns where name in ('ticket', 'word') order by name"),
+      [
+        {'name': 'ticket', 'pattern': '[A-Z]+-\\d+', 'flags': ''},
+        {'name': 'word', 'pattern': 'hello', 'flags': 'i'},
+      ]
+    )
+    self.assertEqual(db.execute("select regexp(regex_named('ticket'), 'see PROJ-12')").fetchone()[0], 1)
+    self.assertEqual(db.execute("select 'HELLO there' regexp regex_named('word')").fetchone()[0], 1)
+    self.assertEqual(db.execute("select regexset_is_match(regexset(regex_named('word'), 'xyz'), 'Hello')").fetchone()[0], 1)
+
+    # other functions don't look up names, so '@name' is still a literal pattern
+    self.assertEqual(db.execute("select regexp('@ticket', 'see PROJ-12')").fetchone()[0], 0)
+    self.assertEqual(db.execute("select regex_find('@ticket', 'mail @ticket')").fetchone()[0], '@ticket')
+    self.assertEqual(db.execute("select regex_print(regex('@word'))").fetchone()[0], '@word')
+    self.assertEqual(db.execute("select regex_valid('@not_defined')").fetchone()[0], 1)
+
+    # redefining replaces the old pattern
+    regex_define('ticket', '#\\d+')
+    self.assertEqual(db.execute("select regex_find(regex_named('ticket'), 'see PROJ-12 and #3')").fetchone()[0], '#3')
+
+    with self.assertRaisesRegex(sqlite3.OperationalError, "Error parsing pattern as regex"):
+      regex_define('bad', '[nope')
+    with self.assertRaisesRegex(sqlite3.OperationalError, "invalid pattern name"):
+      regex_define('not a name', 'a')
+    with self.assertRaisesRegex(sqlite3.OperationalError, "invalid pattern name '1st'"):
+      regex_define('1st', 'a')
+    with self.assertRaisesRegex(sqlite3.OperationalError, "invalid pattern name '@x'"):
+      regex_define('@@x', 'a')
+    with self.assertRaisesRegex(sqlite3.OperationalError, "invalid pattern name ''"):
+      regex_define('@', 'a')
+
+    # regex_define() writes to the database, so it can't be used in schemas
+    db.execute("create view define_view as select regex_define('from_view', 'a')")
+    with self.assertRaisesRegex(sqlite3.OperationalError, "unsafe use of regex_define()"):
+      db.execute("select * from define_view").fetchone()
+    db.execute("drop view define_view")
+
+  def test_regex_undefine(self):
+    db.execute("select regex_define('temporary', 'a')").fetchone()
+    self.assertEqual(db.execute("select regex_undefine('temporary')").fetchone()[0], 1)
+    self.assertEqual(db.execute("select regex_undefine('@temporary')").fetchone()[0], 0)
+    with self.assertRaisesRegex(sqlite3.OperationalError, "regex pattern '@temporary' is not defined"):
+      db.execute("select regex_named('temporary')").fetchone()
+
+  def test_regex_named(self):
+    db.execute("select regex_define('year', '\\b(?P<year>\\d{4})\\b')").fetchone()
+    self.assertEqual(db.execute("select regex_print(regex_named('year'))").fetchone()[0], '\\b(?P<year>\\d{4})\\b')
+    self.assertEqual(
+      execute_all("select regex_capture(captures, 'year') as year from regex_captures(regex_named('year'), '1999 and 2024')"),
+      [{'year': '1999'}, {'year': '2024'}]
+    )
+    with self.assertRaisesRegex(sqlite3.OperationalError, "regex pattern '@nope' is not defined"):
+      db.execute("select regex_named('nope')").fetchone()
+
+  def test_regex_builtin(self):
+    find = lambda name, text: db.execute("select regex_find(regex_builtin(?), ?)", [name, text]).fetchone()[0]
+    # each sample is (text, expected full match or None)
+    SAMPLES = {
+      "email": [("alex@example.com", True), ("first.last+tag@sub.example.co.uk", True), ("alex@localhost", False), ("@example.com", False)],
+      "url": [("https://example.com/path?q=1#frag", True), ("HTTP://example.com", True), ("ftp://example.com", False), ("http://", False)],
+      "domain": [("example.com", True), ("sub.example-site.io", True), ("localhost", False), ("-bad.com", False)],
+      "ipv4": [("192.168.0.1", True), ("255.255.255.255", True), ("0.0.0.0", True), ("256.1.1.1", False), ("1.2.3", False)],
+      "ipv6": [("2001:0db8:85a3:0000:0000:8a2e:0370:7334", True), ("2001:db8::1", True), ("1:2::3:4", True), ("::1", True), ("fe80::", True), ("::", True), ("12345::", False)],
+      "mac_address": [("00:1a:2b:3c:4d:5e", True), ("00-1A-2B-3C-4D-5E", True), ("00:1a:2b:3c:4d", False)],
+      "uuid": [("123e4567-e89b-12d3-a456-426614174000", True), ("123e4567e89b12d3a456426614174000", False), ("123e4567-e89b-12d3-a456-42661417400g", False)],
+      "iso8601_date": [("2023-01-31", True), ("1999-12-01", True), ("2023-13-01", False), ("2023-01-32", False)],
+      "iso8601_time": [("13:45", True), ("13:45:30.250", True), ("24:00", False), ("12:60", False)],
+      "iso8601_datetime": [("2023-01-31T13:45:30Z", True), ("2023-01-31 13:45:30+05:30", True), ("2023-01-31T13:45", True), ("2023-01-31", False)],
+      "semver": [("1.2.3", True), ("1.2.3-beta.1+build.5", True), ("0.0.0-alpha", True), ("1.2", False), ("01.2.3", False)],
+      "us_phone": [("(555) 555-1234", True), ("+1 555.555.1234", True), ("5555551234", True), ("155-555-1234", False), ("555-1234", False)],
+      "us_zip": [("94103", True), ("94103-1234", True), ("9410", False)],
+      "us_ssn": [("123-45-6789", True), ("123456789", False), ("123-456-789", False)],
+      "credit_card": [("4111 1111 1111 1111", True), ("4111-1111-1111-1111", True), ("4111111111111111", True), ("4111 1111", False)],
+      "hex_color": [("#fff", True), ("#1e90ff", True), ("#1e90ffcc", True), ("#ggg", False), ("#12345", False)],
+      "number": [("42", True), ("-1.5e10", True), (".5", True), ("+3.", True), ("abc", False)],
+    }
+    for name, samples in SAMPLES.items():
+      for text, should_match in samples:
+        match = find(name, text)
+        if should_match:
+          self.assertEqual(match, text, f"builtin '{name}' should fully match '{text}'")
+        else:
+          self.assertNotEqual(match, text, f"builtin '{name}' should not fully match '{text}'")
+
+    self.assertEqual(
+      set(SAMPLES.keys()),
+      set(x[0] for x in db.execute("select name from regex_builtins").fetchall())
+    )
+    self.assertEqual(
+      execute_all("select regex_capture(captures, 'major') as major, regex_capture(captures, 'prerelease') as pre from regex_captures(regex_builtin('semver'), 'version 1.22.3-rc.1')"),
+      [{'major': '1', 'pre': 'rc.1'}]
+    )
+    with self.assertRaisesRegex(sqlite3.OperationalError, "'nope' is not a builtin pattern"):
+      db.execute("select regex_builtin('nope')").fetchone()
+
+  def test_regex_builtins(self):
+    rows = execute_all("select rowid, * from regex_builtins")
+    self.assertEqual(rows[0], {
+      'rowid': 0,
+      'name': 'email',
+      'pattern': '[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\\.[A-Za-z0-9-]+)*\\.[A-Za-z]{2,}',
+      'description': "Email address, like 'alex@example.com'",
+    })
+    for row in rows:
+      self.assertEqual(db.execute("select regex_valid(?)", [row['pattern']]).fetchone()[0], 1)
+      self.assertEqual(db.execute("select regex_print(regex_builtin(?))", [row['name']]).fetchone()[0], row['pattern'])
 
   def test_regex_print(self):
     regex_print = lambda pattern: db.execute("select regex_print(regex(?))", [pattern]).fetchone()[0]
     self.assertEqual(regex_print('^\d{4}-\d{2}-\d{2}$'), '^\d{4}-\d{2}-\d{2}$')
 
+  def test_regex_ast(self):
+    regex_ast = lambda pattern: json.loads(db.execute("select regex_ast(?)", [pattern]).fetchone()[0])
+
+    self.assertEqual(
+      regex_ast('a+?')['ast'],
+      {
+        'type': 'repetition', 'span': {'start': 0, 'end': 3}, 'min': 1, 'max': None, 'greedy': False,
+        'children': [{'type': 'literal', 'span': {'start': 0, 'end': 1}, 'value': 'a', 'children': []}]
+      }
+    )
+    root = regex_ast('^(?P<year>\\d{4})|[^a-z]')['ast']
+    self.assertEqual(root['type'], 'alternation')
+    concat, bracketed = root['children']
+    self.assertEqual([c['type'] for c in concat['children']], ['assertion', 'group'])
+    group = concat['children'][1]
+    self.assertEqual((group['capture_index'], group['capture_name'], group['span']), (1, 'year', {'start': 1, 'end': 16}))
+    self.assertEqual(group['children'][0]['children'][0]['name'], 'digit')
+    self.assertEqual((group['children'][0]['min'], group['children'][0]['max']), (4, 4))
+    self.assertEqual((bracketed['type'], bracketed['negated']), ('class_bracketed', True))
+    self.assertEqual(bracketed['children'][0], {'type': 'range', 'span': {'start': 19, 'end': 22}, 'start': 'a', 'end': 'z', 'children': []})
+
+    self.assertEqual(regex_ast('(?i)x')['ast']['children'][0]['flags'], 'i')
+    self.assertEqual(
+      regex_ast('^\\w+$')['features'],
+      {'anchored_start': True, 'anchored_end': True, 'line_anchors': 0, 'unicode_classes': 1, 'unicode_word_boundaries': 0, 'captures': 0, 'literal': False, 'match_empty': False}
+    )
+    self.assertEqual(regex_ast('(?-u:\\w)')['features']['unicode_classes'], 0)
+    # regex() objects work too
+    self.assertEqual(json.loads(db.execute("select regex_ast(regex('x'))").fetchone()[0])['ast']['type'], 'literal')
+    with self.assertRaisesRegex(sqlite3.OperationalError, "Error parsing pattern as regex"):
+      db.execute("select regex_ast('(')").fetchone()
+
+  def test_regex_hir(self):
+    regex_hir = lambda pattern: json.loads(db.execute("select regex_hir(?)", [pattern]).fetchone()[0])
+
+    self.assertEqual(
+      regex_hir('(?i)k'),
+      {
+        'hir': {'type': 'class', 'unicode': True, 'ranges': [['K', 'K'], ['k', 'k'], ['\u212a', '\u212a']], 'children': []},
+        'features': {'anchored_start': False, 'anchored_end': False, 'line_anchors': 0, 'unicode_classes': 1, 'unicode_word_boundaries': 0, 'captures': 0, 'literal': False, 'match_empty': False},
+      }
+    )
+    hir = regex_hir('(?m)^a\\b(b)?')
+    self.assertEqual([c['type'] for c in hir['hir']['children']], ['anchor', 'literal', 'word_boundary', 'repetition'])
+    self.assertEqual(hir['hir']['children'][0]['kind'], 'start_line')
+    self.assertEqual(hir['hir']['children'][2], {'type': 'word_boundary', 'unicode': True, 'negated': False, 'children': []})
+    self.assertEqual(hir['hir']['children'][3]['children'][0]['capture_index'], 1)
+    self.assertEqual(
+      {k: hir['features'][k] for k in ['anchored_start', 'line_anchors', 'unicode_word_boundaries', 'captures']},
+      {'anchored_start': False, 'line_anchors': 1, 'unicode_word_boundaries': 1, 'captures': 1}
+    )
+    self.assertEqual(regex_hir('abc')['features']['literal'], True)
+    self.assertEqual(regex_hir('\\Aabc\\z')['hir']['children'][0]['kind'], 'start_text')
+    with self.assertRaisesRegex(sqlite3.OperationalError, "Error parsing pattern as regex"):
+      db.execute("select regex_hir('[')").fetchone()
+
+  def test_regex_literals(self):
+    regex_literals = lambda pattern: json.loads(db.execute("select regex_literals(?)", [pattern]).fetchone()[0])
+
+    self.assertEqual(
+      regex_literals('^ERROR: .* in (main|test)\\.rs'),
+      {
+        'prefix': 'ERROR: ', 'suffix': '.rs',
+        'prefixes': ['ERROR: '], 'suffixes': [' in main.rs', ' in test.rs'],
+        'required': ['ERROR: ', ' in ', '.rs'],
+        'exact': False, 'anchored_start': True, 'anchored_end': False,
+      }
+    )
+    self.assertEqual(
+      regex_literals('abc'),
+      {
+        'prefix': 'abc', 'suffix': 'abc', 'prefixes': ['abc'], 'suffixes': ['abc'],
+        'required': ['abc'], 'exact': True, 'anchored_start': False, 'anchored_end': False,
+      }
+    )
+    foo = regex_literals('foo|foobar')
+    self.assertEqual((foo['prefix'], foo['prefixes'], foo['required'], foo['exact']), ('foo', ['foo', 'foobar'], [], True))
+    self.assertEqual(regex_literals('a(bc)+d?e')['required'], ['a', 'bc', 'e'])
+    self.assertEqual(regex_literals('^abc\\d+xyz$')['required'], ['abc', 'xyz'])
+    self.assertEqual(regex_literals('(?i)abc')['required'], [])
+    self.assertEqual(regex_literals('.*')['prefix'], '')
+    self.assertEqual(regex_literals('é+')['prefix'], 'é')
+    self.assertEqual(regex_literals('x?é$')['suffix'], 'é')
+    # the common suffix of 'éz' and 'ɩz' starts in the middle of a character
+    self.assertEqual(regex_literals('[éɩ]z$')['suffix'], 'z')
+    self.assertEqual(json.loads(db.execute("select regex_literals(regex('x\\d'))").fetchone()[0])['prefix'], 'x')
+
+    # prefilter with LIKE before running the regex
+    self.assertEqual(
+      [row[0] for row in db.execute("""
+        with lines(line) as (values ('ERROR: oops in main.rs'), ('WARN: oops in main.rs'), ('ERROR: in lib.rs'))
+        select line from lines
+        where line like (regex_literals(?1) ->> 'prefix') || '%' and regexp(?1, line)
+      """, ['^ERROR: .* in (main|test)\\.rs']).fetchall()],
+      ['ERROR: oops in main.rs']
+    )
+    with self.assertRaisesRegex(sqlite3.OperationalError, "pattern not valid regex"):
+      db.execute("select regex_literals('(')").fetchone()
+
   def test_regexset(self):
     regexset = lambda *patterns: db.execute("select regexset({args})".format(args=spread_args(patterns)), patterns).fetchone()[0]
     self.assertEqual(regexset('a'), None)
@@ -95,6 +448,45 @@ class TestRegex(unittest.TestCase):
     regexset_print = lambda *patterns: db.execute("select regexset_print(regexset({args}))".format(args=spread_args(patterns)), patterns).fetchone()[0]
     self.assertEqual(regexset_print('a', 'b', 'c'), '["a","b","c"]')
 
+  def test_regex_redact(self):
+    regex_redact = lambda *args: db.execute("select regex_redact({args})".format(args=spread_args(args)), args).fetchone()[0]
+    redact_set = lambda patterns, *args: db.execute(
+      "select regex_redact(regexset({patterns}), {args})".format(patterns=spread_args(patterns), args=spread_args(args)),
+      [*patterns, *args]
+    ).fetchone()[0]
+
+    self.assertEqual(regex_redact(r'\d+', 'a 12 b 345'), 'a ** b ***')
+    self.assertEqual(regex_redact(r'\d+', 'a 12 b 345', None), 'a ** b ***')
+    self.assertEqual(regex_redact(r'\d+', 'a 12 b 345', '#'), 'a # b #')
+    self.assertEqual(regex_redact(r'\d+', 'a 12 b 345', '<{label}>'), 'a <0> b <0>')
+    self.assertEqual(regex_redact(r'(?P<num>\d+)', 'a 12', '<{label}>'), 'a <num>')
+    self.assertEqual(regex_redact(r'é+', 'caféé!'), 'caf**!')
+    self.assertEqual(regex_redact(r'x*', 'abc'), 'abc')
+    self.assertEqual(db.execute("select regex_redact(regex('a', 'i'), 'AaB')").fetchone()[0], '**B')
+
+    self.assertEqual(
+      redact_set([r'(?P<EMAIL>\w+@\w+\.com)', r'(?P<PHONE>\d{3}-\d{4})'], 'bob@x.com or 555-1234', '<{label}>'),
+      '<EMAIL> or <PHONE>'
+    )
+    self.assertEqual(redact_set(['a', 'b'], 'abcab', '<{label}>'), '<0><1>c<0><1>')
+    self.assertEqual(redact_set(['zz', 'b'], 'abc', '<{label}>'), 'a<1>c')
+    # overlapping matches are merged, labeled by the earliest then longest
+    self.assertEqual(redact_set(['abc', 'bcd'], 'xabcdx', '<{label}>'), 'x<0>x')
+    self.assertEqual(redact_set(['bc', 'abcd'], 'xabcdx', '<{label}>'), 'x<1>x')
+    self.assertEqual(redact_set(['ab', 'ab'], 'xabx', '<{label}>'), 'x<0>x')
+    self.assertEqual(redact_set(['abc', 'bcd'], 'xabcdx'), 'x****x')
+    self.assertEqual(redact_set(['abc'], 'xyz', '#'), 'xyz')
+
+    self.assertEqual(
+      execute_all(
+        "select regex_redact(regexset('\\d+', '[aeiou]'), value, '_') as r from json_each(?)",
+        ['["a1", "b22", "c"]']
+      ),
+      [{'r': '__'}, {'r': 'b_'}, {'r': 'c'}]
+    )
+    with self.assertRaisesRegex(sqlite3.OperationalError, "pattern not valid regex"):
+      regex_redact('[', 'abc')
+
   def test_regexset_is_match(self):
     regexset_is_match = lambda *patterns, text: db.execute("select regexset_is_match(regexset({args}), ?)".format(args=spread_args(patterns)), [*patterns, text]).fetchone()[0]
     self.assertEqual(regexset_is_match('a', text='bbb'), 0)
@@ -127,6 +519,43 @@ class TestRegex(unittest.TestCase):
       0
     )
 
+  def test_regex_full_match(self):
+    regex_full_match = lambda pattern, content: db.execute("select regex_full_match(?, ?)", [pattern, content]).fetchone()[0]
+    self.assertEqual(regex_full_match(r'\d{3}', '123'), 1)
+    self.assertEqual(regex_full_match(r'\d{3}', '1234'), 0)
+    self.assertEqual(regex_full_match(r'\d{3}', 'a123'), 0)
+    # every branch of an alternation is anchored, not just the first and last
+    self.assertEqual(regex_full_match('a|ab', 'ab'), 1)
+    self.assertEqual(regex_full_match('foo|bar', 'foobar'), 0)
+    # anchors are to the entire text, even with the m flag
+    self.assertEqual(regex_full_match('(?m)abc', 'abc\n'), 0)
+    self.assertEqual(regex_full_match('(?m)abc$', 'abc\nabc'), 0)
+    self.assertEqual(regex_full_match('', ''), 1)
+    # a trailing comment in x mode doesn't hide the anchor
+    self.assertEqual(regex_full_match('(?x) a b  # letters', 'ab'), 1)
+    self.assertEqual(regex_full_match('(?x) a b  # letters', 'abc'), 0)
+    self.assertEqual(regex_full_match('(?x) a \\  b', 'a b'), 1)
+
+    self.assertEqual(db.execute("select regex_full_match(regex('ABC', 'i'), 'abc')").fetchone()[0], 1)
+    self.assertEqual(db.execute("select regex_full_match(regex('ABC', 'i'), 'abcd')").fetchone()[0], 0)
+
+    db.execute("select regex_define('code', '[A-Z]{3}')")
+    self.assertEqual(db.execute("select regex_full_match(regex_named('code'), 'ABC')").fetchone()[0], 1)
+    self.assertEqual(db.execute("select regex_full_match(regex_named('code'), 'ABCD')").fetchone()[0], 0)
+    db.execute("select regex_undefine('code')")
+
+    # different patterns on each row aren't mixed up by the cache
+    self.assertEqual(
+      execute_all(
+        "select regex_full_match(value ->> 0, value ->> 1) as m from json_each(?)",
+        ['[["a+", "aaa"], ["b+", "aaa"], ["a+", "aab"], ["a+", "aa"]]']
+      ),
+      [{'m': 1}, {'m': 0}, {'m': 0}, {'m': 1}]
+    )
+
+    with self.assertRaisesRegex(sqlite3.OperationalError, "pattern not valid regex"):
+      regex_full_match('[nope', 'abc')
+
   def test_regex_find(self):
     regex_find = lambda pattern, content: db.execute("select regex_find(?, ?)", [pattern, content]).fetchone()[0]
     self.assertEqual(
@@ -151,6 +580,55 @@ class TestRegex(unittest.TestCase):
     with self.assertRaisesRegex(sqlite3.OperationalError, "pattern not valid regex"):
       regex_find_at("[invalidregex", "abc", 0)
 
+  def test_regex_find_nth(self):
+    regex_find_nth = lambda pattern, content, n: db.execute("select regex_find_nth(?, ?, ?)", [pattern, content, n]).fetchone()[0]
+    self.assertEqual(regex_find_nth(r'\d+', 'a1 b22 c333', 1), '1')
+    self.assertEqual(regex_find_nth(r'\d+', 'a1 b22 c333', 3), '333')
+    self.assertEqual(regex_find_nth(r'\d+', 'a1 b22 c333', 4), None)
+    self.assertEqual(regex_find_nth(r'\d+', 'a1 b22 c333', -1), '333')
+    self.assertEqual(regex_find_nth(r'\d+', 'a1 b22 c333', -3), '1')
+    self.assertEqual(regex_find_nth(r'\d+', 'a1 b22 c333', -4), None)
+    self.assertEqual(regex_find_nth(r'\d+', 'abc', -1), None)
+    self.assertEqual(regex_find_nth(r'\d+', 'a1 b22 c333', 9223372036854775807), None)
+    self.assertEqual(regex_find_nth(r'\d+', 'a1 b22 c333', -9223372036854775808), None)
+
+    with self.assertRaisesRegex(sqlite3.OperationalError, "n is 1-based, use 1 for the first match or -1 for the last"):
+      regex_find_nth(r'\d+', 'a1', 0)
+    with self.assertRaisesRegex(sqlite3.OperationalError, "expected n to be an integer"):
+      regex_find_nth(r'\d+', 'a1', 'x')
+    with self.assertRaisesRegex(sqlite3.OperationalError, "pattern not valid regex"):
+      regex_find_nth("[invalidregex", "abc", 1)
+
+    # deterministic, so it can be used in generated columns
+    db.execute("create table find_nth_test(t text, second text as (regex_find_nth('\\w+', t, 2)))")
+    db.execute("insert into find_nth_test(t) values ('hello big world')")
+    self.assertEqual(db.execute("select second from find_nth_test").fetchone()[0], 'big')
+    db.execute("drop table find_nth_test")
+
+  def test_regex_extract_all(self):
+    regex_extract_all = lambda *args: db.execute("select regex_extract_all({args})".format(args=spread_args(args)), args).fetchone()[0]
+    self.assertEqual(regex_extract_all(r'#\w+', 'a #b c #d #e'), '["#b","#d","#e"]')
+    self.assertEqual(regex_extract_all(r'#(\w+)', 'a #b c #d #e', 1), '["b","d","e"]')
+    self.assertEqual(regex_extract_all(r'#(?P<tag>\w+)', 'a #b c #d', 'tag'), '["b","d"]')
+    self.assertEqual(regex_extract_all(r'#(?P<tag>\w+)', 'a #b c #d', 0), '["#b","#d"]')
+    self.assertEqual(regex_extract_all(r'#\w+', 'nothing'), '[]')
+    self.assertEqual(regex_extract_all(r'(a)|(b)', 'ab', 2), '[null,"b"]')
+    self.assertEqual(
+      db.execute("select json_array_length(regex_extract_all(regex('x', 'i'), 'xXx'))").fetchone()[0],
+      3
+    )
+    self.assertEqual(
+      db.execute("select json_type(regex_extract_all('x', 'x') -> 0)").fetchone()[0],
+      'text'
+    )
+
+    with self.assertRaisesRegex(sqlite3.OperationalError, "no capture group at index 2 in pattern"):
+      regex_extract_all(r'#(\w+)', 'a #b', 2)
+    with self.assertRaisesRegex(sqlite3.OperationalError, "no capture group named 'nope' in pattern"):
+      regex_extract_all(r'#(?P<tag>\w+)', 'a #b', 'nope')
+    with self.assertRaisesRegex(sqlite3.OperationalError, "pattern not valid regex"):
+      regex_extract_all('[', 'abc')
+
   def test_regex_capture(self):
     regex_capture = lambda pattern, content, group: db.execute("select regex_capture(?, ?, ?)", [pattern, content, group]).fetchone()[0]
     MOVIE_PATTERN = "'(?P<title>[^']+)'\s+\((?P<year>\d{4})\)"
@@ -220,6 +698,26 @@ class TestRegex(unittest.TestCase):
     #with self.assertRaisesRegex(sqlite3.OperationalError, "pattern not valid regex"):
     #  regex_find("[invalidregex", "abc")
 
+    regex_replace_ext = lambda pattern, content, replacement: db.execute("select regex_replace(?, ?, ?, 'ext')", [pattern, content, replacement]).fetchone()[0]
+    self.assertEqual(regex_replace_ext(r'(\w+) (\w+)', 'hello WORLD', r'\U$1\E \u\L$2'), 'HELLO World')
+    self.assertEqual(regex_replace_ext(r'(\w+)', 'x', r'\$1 $$1 \\ \t\n'), '$1 $1 \\ \t\n')
+    # conditional text for optional groups
+    self.assertEqual(regex_replace_ext(r'(?P<n>\d+)(?P<unit>kg)?', '12', '${n} ${unit:-units}'), '12 units')
+    self.assertEqual(regex_replace_ext(r'(?P<n>\d+)(?P<unit>kg)?', '12kg', '${n} ${unit:-units}'), '12 kg')
+    self.assertEqual(regex_replace_ext(r'(\d+)(kg)?', '12kg', r'$1${2:+ (\U$2\})}'), '12 (KG})')
+    self.assertEqual(regex_replace_ext(r'(\d+)(kg)?', '12', r'$1${2:+ (\U$2\})}'), '12')
+    # NULL or '' mode is the default syntax
+    self.assertEqual(db.execute(r"select regex_replace('(\w+)', 'hi', '\U$1', null)").fetchone()[0], r'\Uhi')
+
+    with self.assertRaisesRegex(sqlite3.OperationalError, "unknown replacement mode 'perl', expected 'ext'"):
+      db.execute("select regex_replace('a', 'a', 'b', 'perl')").fetchone()
+    with self.assertRaisesRegex(sqlite3.OperationalError, "unknown escape '\\\\q' in replacement"):
+      regex_replace_ext('a', 'a', r'\q')
+    with self.assertRaisesRegex(sqlite3.OperationalError, "unclosed '\\$\\{' in replacement"):
+      regex_replace_ext('(a)', 'a', '${1:+x')
+    with self.assertRaisesRegex(sqlite3.OperationalError, "expected ':\\+' or ':-'"):
+      regex_replace_ext('(a)', 'a', '${1:x}')
+
   def test_regex_replace_all(self):
     regex_replace_all = lambda pattern, content, replacement: db.execute("select regex_replace_all(?, ?, ?)", [pattern, content, replacement]).fetchone()[0]
 
@@ -227,6 +725,485 @@ class TestRegex(unittest.TestCase):
       regex_replace_all('a', 'abc abc', ''),
       'bc bc'
     )
+    # snake_case to CamelCase and back
+    self.assertEqual(
+      db.execute("select regex_replace_all('(?:^|_)([a-z])', 'snake_case_id', '\\u$1', 'ext')").fetchone()[0],
+      'SnakeCaseId'
+    )
+    self.assertEqual(
+      db.execute("select regex_replace_all('([a-z])([A-Z])', 'camelCaseId', '${1}_\\l$2', 'ext')").fetchone()[0],
+      'camel_case_id'
+    )
+    # a constant template is parsed once and reused, templates from columns for each row
+    self.assertEqual(
+      [row[0] for row in db.execute("select regex_replace_all('\\w+', value, '\\U$0', 'ext') from json_each(?)", [json.dumps(['ab', 'cd ef'])])],
+      ['AB', 'CD EF']
+    )
+    self.assertEqual(
+      [row[0] for row in db.execute("select regex_replace('\\w+', 'ab', value, 'ext') from json_each(?)", [json.dumps(['\\U$0', '<$0>'])])],
+      ['AB', '<ab>']
+    )
+
+  def test_regex_replace_all_fn(self):
+    emoji = {'smile': '😄', 'tada': '🎉'}
+    db.create_function('test_emoji', 2, lambda match, captures: emoji.get(json.loads(captures)[1]))
+    db.create_function('test_mask', 1, lambda match: '#' * len(match))
+    db.create_function('test_fail', 1, lambda match: 1 / 0)
+
+    self.assertEqual(
+      db.execute(r"select regex_replace_all_fn(':(\w+):', 'hi :smile: :nope: :tada:', 'test_emoji')").fetchone()[0],
+      'hi 😄 :nope: 🎉'
+    )
+    self.assertEqual(
+      db.execute(r"select regex_replace_all_fn('\d{3}-\d{4}', 'call 555-1234 now', 'test_mask')").fetchone()[0],
+      'call ######## now'
+    )
+    # builtin functions work too, and non-text results are converted
+    self.assertEqual(db.execute(r"select regex_replace_all_fn('[a-z]+', 'ab-cde', 'length')").fetchone()[0], '2-3')
+    self.assertEqual(db.execute("select regex_replace_all_fn('x', 'abc', 'test_fail')").fetchone()[0], 'abc')
+
+    with self.assertRaisesRegex(sqlite3.OperationalError, "no SQL function named 'nope' that takes 1 or 2 arguments"):
+      db.execute("select regex_replace_all_fn('a', 'a', 'nope')").fetchone()
+    with self.assertRaisesRegex(sqlite3.OperationalError, "no SQL function named"):
+      db.execute("""select regex_replace_all_fn('a', 'a', 'x"(1); --')""").fetchone()
+    with self.assertRaisesRegex(sqlite3.OperationalError, "user-defined function raised exception"):
+      db.execute("select regex_replace_all_fn('a', 'a', 'test_fail')").fetchone()
+
+  def test_regex_replace_map(self):
+    regex_replace_map = lambda pattern, content, json_map: db.execute("select regex_replace_map(?, ?, ?)", [pattern, content, json_map]).fetchone()[0]
+    self.assertEqual(
+      regex_replace_map(r'\b[A-Z]{2}\b', 'NY to CA via TX', '{"NY": "New York", "CA": "California", "TX": null}'),
+      'New York to California via TX'
+    )
+    self.assertEqual(regex_replace_map(r'\d+', '1 2 3', '{"1": 10, "2": [true]}'), '10 [true] 3')
+    # the parsed map is reused across rows
+    self.assertEqual(
+      [row[0] for row in db.execute("""select regex_replace_map('\\w+', value, '{"a": "A"}') from json_each('["a b", "b a"]')""")],
+      ['A b', 'b A']
+    )
+    with self.assertRaisesRegex(sqlite3.OperationalError, "expected json_map to be a JSON object"):
+      regex_replace_map('a', 'a', '[1]')
+    with self.assertRaisesRegex(sqlite3.OperationalError, "value is not a regex_map object"):
+      db.execute("select regex_replace_map('a', 'a')").fetchone()
+
+  def test_regex_map_agg(self):
+    db.execute("create temp table abbreviations(abbr text, expansion text)")
+    db.executemany("insert into abbreviations values (?, ?)", [
+      ('St', 'Saint'),
+      ('St.', 'Street'),
+      ('Ave', 'Avenue'),
+      ('NY', None),
+      ('', 'empty'),
+      ('St', 'Street'),
+    ])
+    self.assertEqual(
+      db.execute("""
+        select regex_replace_map(
+          (select regex_map_agg(abbr, expansion) from abbreviations),
+          '12 Main St. and 3rd Ave, St Louis, NY'
+        )
+      """).fetchone()[0],
+      '12 Main Street and 3rd Avenue, Street Louis, NY'
+    )
+    # an empty group doesn't replace anything
+    self.assertEqual(
+      db.execute("select regex_replace_map((select regex_map_agg(abbr, expansion) from abbreviations where 0), 'St.')").fetchone()[0],
+      'St.'
+    )
+    db.execute("drop table temp.abbreviations")
+
+  def test_regex_compose(self):
+    compose_find = lambda template, subpatterns, text: db.execute("select regex_find(regex_compose(?, ?), ?)", [template, subpatterns, text]).fetchone()[0]
+    compose_print = lambda template, subpatterns: db.execute("select regex_print(regex_compose(?, ?))", [template, subpatterns]).fetchone()[0]
+
+    self.assertEqual(
+      compose_print('{{ip}} - - \\[{{ts}}\\]', '{"ip": "\\\\d+(\\\\.\\\\d+){3}", "ts": "[^\\\\]]+"}'),
+      '(?:\\d+(\\.\\d+){3}) - - \\[(?:[^\\]]+)\\]'
+    )
+    self.assertEqual(
+      compose_find('{{ip}} - - \\[{{ts}}\\]', '{"ip": "\\\\d+(\\\\.\\\\d+){3}", "ts": "[^\\\\]]+"}', '127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET /"'),
+      '127.0.0.1 - - [10/Oct/2000:13:55:36 -0700]'
+    )
+    # sub-patterns are grouped, so alternations don't leak into the template
+    self.assertEqual(compose_find('^{{x}}$', '{"x": "a|b"}', 'ab'), None)
+    self.assertEqual(compose_find('^{{ x }}c$', '{"x": "a|b"}', 'bc'), 'bc')
+    # the same placeholder can be used more than once
+    self.assertEqual(compose_print('{{d}}-{{d}}', '{"d": "\\\\d"}'), '(?:\\d)-(?:\\d)')
+    # json_object() works as well
+    self.assertEqual(
+      db.execute("select regex_print(regex_compose('{{a}}{{b}}', json_object('a', 'x', 'b', 'y')))").fetchone()[0],
+      '(?:x)(?:y)'
+    )
+    # named patterns can be passed in with regex_named()
+    db.execute("select regex_define('year', '\\d{4}')")
+    self.assertEqual(
+      db.execute("select regex_find(regex_compose('y{{y}}', json_object('y', regex_print(regex_named('year')))), 'in y2023')").fetchone()[0],
+      'y2023'
+    )
+    db.execute("select regex_undefine('year')")
+
+    with self.assertRaisesRegex(sqlite3.OperationalError, "no sub-pattern provided for placeholder '{{ts}}'"):
+      compose_print('{{ip}} {{ts}}', '{"ip": "x"}')
+    with self.assertRaisesRegex(sqlite3.OperationalError, "unclosed placeholder"):
+      compose_print('{{ip', '{"ip": "x"}')
+    with self.assertRaisesRegex(sqlite3.OperationalError, "expected sub-pattern 'ip' to be a string"):
+      compose_print('{{ip}}', '{"ip": 1}')
+    with self.assertRaisesRegex(sqlite3.OperationalError, "expected subpatterns to be a JSON object"):
+      compose_print('{{ip}}', '[1]')
+    with self.assertRaisesRegex(sqlite3.OperationalError, "Error parsing composed pattern as regex"):
+      compose_print('{{a}}', '{"a": "("}')
+
+  def test_regex_from_like(self):
+    regex_from_like = lambda *args: db.execute(f"select regex_print(regex_from_like({spread_args(args)}))", args).fetchone()[0]
+    self.assertEqual(regex_from_like('a%b_c.'), '(?s)^[aA].*[bB].[cC]\\.$')
+    self.assertEqual(regex_from_like('10!%%', '!'), '(?s)^10%.*$')
+
+    TEXTS = ['', 'abc', 'ABC', 'aXc', 'a\nc', 'abcd', '10%', '100', 'ä', 'Ä', 'a_c', 'a.c', '(a)']
+    CASES = [('abc', None), ('a_c', None), ('a%', None), ('%c', None), ('%', None), ('_', None), ('ä', None),
+             ('a\\_c', '\\'), ('10!%', '!'), ('a.c', None), ('(%)', None), ('a__c', 'a')]
+    for like, escape in CASES:
+      for text in TEXTS:
+        if escape is None:
+          expected = db.execute("select ? like ?", [text, like]).fetchone()[0]
+          actual = db.execute("select regexp(regex_from_like(?), ?)", [like, text]).fetchone()[0]
+        else:
+          expected = db.execute("select ? like ? escape ?", [text, like, escape]).fetchone()[0]
+          actual = db.execute("select regexp(regex_from_like(?, ?), ?)", [like, escape, text]).fetchone()[0]
+        self.assertEqual(actual, expected, f"{text!r} LIKE {like!r} ESCAPE {escape!r}")
+
+    self.assertEqual(
+      execute_all("select start, end, match from regex_find_all(regex_from_like('a%c'), 'abc')"),
+      [{'start': 0, 'end': 3, 'match': 'abc'}]
+    )
+    with self.assertRaisesRegex(sqlite3.OperationalError, "ESCAPE expression must be a single character"):
+      regex_from_like('a', '!!')
+    with self.assertRaisesRegex(sqlite3.OperationalError, "can't end with the escape character"):
+      regex_from_like('a!', '!')
+
+  def test_regex_from_glob(self):
+    regex_from_glob = lambda pattern: db.execute("select regex_print(regex_from_glob(?))", [pattern]).fetchone()[0]
+    self.assertEqual(regex_from_glob('*.[ch]'), '(?s)^.*\\.[ch]$')
+
+    TEXTS = ['', 'main.c', 'main.h', 'MAIN.C', 'main.rs', 'a/b.c', 'x', 'xy', ']', '-', 'b', '[', 'a\nb']
+    GLOBS = ['*.c', '*.[ch]', '?', '??', '[]]', '[^]]', '[a-c]', '[^a-c]', '[a-]', '[', '*', 'a*b', 'main.?']
+    for glob in GLOBS:
+      for text in TEXTS:
+        expected = db.execute("select ? glob ?", [text, glob]).fetchone()[0]
+        actual = db.execute("select regexp(regex_from_glob(?), ?)", [glob, text]).fetchone()[0]
+        self.assertEqual(actual, expected, f"{text!r} GLOB {glob!r}")
+
+    self.assertEqual(
+      db.execute("select regexset_is_match(regexset(regex_from_glob('*.c'), regex_from_like('%.RS')), 'lib.rs')").fetchone()[0],
+      1
+    )
+
+  def test_regex_create_collation(self):
+    self.assertEqual(
+      db.execute("select regex_create_collation('ticket', '(?P<prefix>[A-Z]+)-(?P<num>\\d+)', 'prefix:text,num:int')").fetchone()[0],
+      'ticket'
+    )
+    self.assertEqual(
+      [row[0] for row in db.execute("""
+        select value
+        from json_each('["PROJ-10", "PROJ-2", "misc", "ABC-3", "PROJ-1"]')
+        order by value collate ticket
+      """)],
+      ['ABC-3', 'PROJ-1', 'PROJ-2', 'PROJ-10', 'misc']
+    )
+    # groups by index, and real numbers
+    db.execute("select regex_create_collation('version', regex('v?(\\d+)\\.(\\d+)(?:\\.(\\d+))?'), '1:int, 2:int, 3:real')")
+    self.assertEqual(
+      [row[0] for row in db.execute("""
+        select value
+        from json_each('["1.10", "v1.9.5", "1.9", "0.2"]')
+        order by value collate version
+      """)],
+      ['0.2', '1.9', 'v1.9.5', '1.10']
+    )
+    self.assertEqual(db.execute("select 'PROJ-02' = 'PROJ-2' collate ticket").fetchone()[0], 0)
+
+    with self.assertRaisesRegex(sqlite3.OperationalError, "a collation with that name already exists"):
+      db.execute("select regex_create_collation('ticket', '(\\d+)', '1:int')").fetchone()
+    with self.assertRaisesRegex(sqlite3.OperationalError, "unknown collation type 'number'"):
+      db.execute("select regex_create_collation('x', '(\\d+)', '1:number')").fetchone()
+    with self.assertRaisesRegex(sqlite3.OperationalError, "no capture group named 'num'"):
+      db.execute("select regex_create_collation('x', '(\\d+)', 'num:int')").fetchone()
+    with self.assertRaisesRegex(sqlite3.OperationalError, "no capture group at index 2"):
+      db.execute("select regex_create_collation('x', '(\\d+)', '2:int')").fetchone()
+    with self.assertRaisesRegex(sqlite3.OperationalError, "expected collation spec entries like 'group:type'"):
+      db.execute("select regex_create_collation('x', '(\\d+)', '1')").fetchone()
+
+  def test_regex_matches_agg(self):
+    db.execute("create temp table posts(user text, body text)")
+    db.executemany("insert into posts values (?, ?)", [
+      ('alex', 'hello #sqlite #regex'),
+      ('alex', 'no tags here'),
+      ('alex', None),
+      ('brian', '#rust all day, #rust'),
+    ])
+    self.assertEqual(
+      execute_all("select user, regex_matches_agg('#\\w+', body) as tags from posts group by user order by user"),
+      [
+        {'user': 'alex', 'tags': '["#sqlite","#regex"]'},
+        {'user': 'brian', 'tags': '["#rust","#rust"]'},
+      ]
+    )
+    self.assertEqual(
+      db.execute("select regex_matches_agg(regex('#(\\w+)'), body) from posts where user = 'brian'").fetchone()[0],
+      '["#rust","#rust"]'
+    )
+    # empty groups return an empty array, like json_group_array()
+    self.assertEqual(db.execute("select regex_matches_agg('#\\w+', body) from posts where 0").fetchone()[0], '[]')
+    self.assertEqual(
+      db.execute("select json_array_length(regex_matches_agg('#\\w+', body)) from posts").fetchone()[0],
+      4
+    )
+    with self.assertRaisesRegex(sqlite3.OperationalError, "Error parsing regex"):
+      db.execute("select regex_matches_agg('[', body) from posts").fetchone()
+    db.execute("drop table temp.posts")
+
+  def test_regex_count_agg(self):
+    self.assertEqual(
+      db.execute("select regex_count_agg('\\d+', value) from json_each('[\"1 2 3\", null, \"x\", \"45\"]')").fetchone()[0],
+      4
+    )
+    self.assertEqual(db.execute("select regex_count_agg('\\d+', value) from json_each('[]')").fetchone()[0], 0)
+    self.assertEqual(
+      execute_all("select key % 2 as k, regex_count_agg(regex('a'), value) as n from json_each('[\"a\", \"aa\", \"aaa\"]') group by 1 order by 1"),
+      [{'k': 0, 'n': 4}, {'k': 1, 'n': 2}]
+    )
+
+  def test_regex_union_agg(self):
+    db.execute("create temp table blocklist(term text, is_literal int, name text)")
+    db.executemany("insert into blocklist values (?, ?, ?)", [
+      ('foo', 1, None),
+      ('foobar', 1, None),
+      ('a.b', 1, None),
+      ('foo', 1, None),
+      ('\\d{3}', 0, None),
+      (None, 1, None),
+    ])
+    self.assertEqual(
+      db.execute("select regex_print(regex_union_agg(term, is_literal)) from blocklist").fetchone()[0],
+      '(?:foobar)|(?:foo)|(?:a\\.b)|(?:\\d{3})'
+    )
+    self.assertEqual(
+      execute_all("select start, end, match from regex_find_all((select regex_union_agg(term, is_literal) from blocklist), 'foobar foo a.b axb 1234')"),
+      [
+        {'start': 0, 'end': 6, 'match': 'foobar'},
+        {'start': 7, 'end': 10, 'match': 'foo'},
+        {'start': 11, 'end': 14, 'match': 'a.b'},
+        {'start': 19, 'end': 22, 'match': '123'},
+      ]
+    )
+    # without is_literal, rows are patterns
+    self.assertEqual(
+      db.execute("select regex_print(regex_union_agg(term)) from blocklist where term != 'foo'").fetchone()[0],
+      '(?:foobar)|(?:a.b)|(?:\\d{3})'
+    )
+    # named groups per row
+    self.assertEqual(
+      db.execute("select regex_print(regex_union_agg(value ->> 0, 1, value ->> 1)) from json_each('[[\"cat\", \"animal\"], [\"red\", \"color\"]]')").fetchone()[0],
+      '(?P<animal>cat)|(?P<color>red)'
+    )
+    self.assertEqual(
+      db.execute("""
+        select regex_capture(
+          (select regex_union_agg(value ->> 0, 1, value ->> 1) from json_each('[["cat", "animal"], ["red", "color"]]')),
+          'a red car',
+          'color'
+        )
+      """).fetchone()[0],
+      'red'
+    )
+    self.assertEqual(db.execute("select regex_union_agg(term) from blocklist where 0").fetchone()[0], None)
+    with self.assertRaisesRegex(sqlite3.OperationalError, "Error parsing union of patterns as regex"):
+      db.execute("select regex_union_agg(value) from json_each('[\"a\", \"(\"]')").fetchone()
+    # rows with the same name share one group, longest literals first
+    db.execute("create temp table terms(term text, is_literal int, category text)")
+    db.executemany("insert into terms values (?, ?, ?)", [
+      ('red', 1, 'color'),
+      ('cat', 1, 'animal'),
+      ('blue', 1, 'color'),
+      ('horse', 1, 'animal'),
+      ('(?i)green', 0, 'color'),
+      ('car', 1, None),
+    ])
+    self.assertEqual(
+      db.execute("select regex_print(regex_union_agg(term, is_literal, category)) from terms").fetchone()[0],
+      '(?P<animal>horse|cat)|(?P<color>blue|red|(?:(?i)green))|(?:car)'
+    )
+    self.assertEqual(
+      execute_all("""
+        select
+          regex_capture(captures, 'animal') as animal,
+          regex_capture(captures, 'color') as color
+        from regex_captures((select regex_union_agg(term, is_literal, category) from terms), 'a red car, a blue horse and a GREEN cat')
+      """),
+      [
+        {'animal': None, 'color': 'red'},
+        {'animal': None, 'color': None},
+        {'animal': None, 'color': 'blue'},
+        {'animal': 'horse', 'color': None},
+        {'animal': None, 'color': 'GREEN'},
+        {'animal': 'cat', 'color': None},
+      ]
+    )
+    db.execute("drop table temp.terms")
+    with self.assertRaisesRegex(sqlite3.OperationalError, "invalid group name 'not valid'"):
+      db.execute("select regex_union_agg('a', 1, 'not valid')").fetchone()
+    with self.assertRaisesRegex(sqlite3.OperationalError, "invalid group name '1st'"):
+      db.execute("select regex_union_agg('a', 1, '1st')").fetchone()
+    db.execute("drop table temp.blocklist")
+
+  def test_regex_find_window(self):
+    lines = '["what is your refund", "policy on returns", "thanks", null, "refund policy"]'
+    self.assertEqual(
+      [row[0] for row in db.execute(f"""
+        select regex_find_window('refund\\s+policy', value) over (order by key rows between 1 preceding and current row)
+        from json_each('{lines}')
+      """)],
+      ['[]', '["refund\\npolicy"]', '[]', '[]', '["refund policy"]']
+    )
+    self.assertEqual(
+      [row[0] for row in db.execute(f"""
+        select regex_find_window('refund policy', value, ' ') over (order by key rows between 2 preceding and current row)
+        from json_each('{lines}')
+      """)],
+      ['[]', '["refund policy"]', '["refund policy"]', '[]', '["refund policy"]']
+    )
+    # as a plain aggregate, every row is combined
+    self.assertEqual(
+      db.execute(f"select regex_find_window('\\w+ \\w+', value, ' ') from json_each('{lines}')").fetchone()[0],
+      '["what is","your refund","policy on","returns thanks","refund policy"]'
+    )
+    self.assertEqual(db.execute("select regex_find_window('a', value) from json_each('[]')").fetchone()[0], '[]')
+    with self.assertRaisesRegex(sqlite3.OperationalError, "Error parsing regex"):
+      db.execute("select regex_find_window('(', value) from json_each('[\"a\"]')").fetchone()
+
+  def test_regex_capture_names(self):
+    self.assertEqual(
+      execute_all("select rowid, * from regex_capture_names('(?P<year>\\d{4})-(\\d{2})(?:-(?P<day>\\d{2}))?')"),
+      [
+        {'rowid': 0, 'group_index': 0, 'name': None, 'optional': 0},
+        {'rowid': 1, 'group_index': 1, 'name': 'year', 'optional': 0},
+        {'rowid': 2, 'group_index': 2, 'name': None, 'optional': 0},
+        {'rowid': 3, 'group_index': 3, 'name': 'day', 'optional': 1},
+      ]
+    )
+    self.assertEqual(
+      execute_all("select group_index, optional from regex_capture_names(regex('(a)|(b)(c)+((d)){0,2}'))"),
+      [
+        {'group_index': 0, 'optional': 0},
+        {'group_index': 1, 'optional': 1},
+        {'group_index': 2, 'optional': 1},
+        {'group_index': 3, 'optional': 1},
+        {'group_index': 4, 'optional': 1},
+        {'group_index': 5, 'optional': 1},
+      ]
+    )
+    self.assertEqual(
+      execute_all("select group_index, optional from regex_capture_names('(a)+(b){1,}')"),
+      [{'group_index': 0, 'optional': 0}, {'group_index': 1, 'optional': 0}, {'group_index': 2, 'optional': 0}]
+    )
+    self.assertEqual(
+      execute_all("select group_index, name from regex_capture_names('abc')"),
+      [{'group_index': 0, 'name': None}]
+    )
+    with self.assertRaisesRegex(sqlite3.OperationalError, "Error parsing regex"):
+      db.execute("select * from regex_capture_names('(')").fetchall()
+
+  def test_regex_capture_nth(self):
+    regex_capture_nth = lambda pattern, content, n, group: db.execute("select regex_capture_nth(?, ?, ?, ?)", [pattern, content, n, group]).fetchone()[0]
+    MOVIES = "'Citizen Kane' (1941), 'The Wizard of Oz' (1939), 'M' (1931)."
+    PATTERN = r"'(?P<title>[^']+)'\s+\((?P<year>\d{4})\)"
+    self.assertEqual(regex_capture_nth(PATTERN, MOVIES, 1, 'title'), 'Citizen Kane')
+    self.assertEqual(regex_capture_nth(PATTERN, MOVIES, 2, 'title'), 'The Wizard of Oz')
+    self.assertEqual(regex_capture_nth(PATTERN, MOVIES, 2, 2), '1939')
+    self.assertEqual(regex_capture_nth(PATTERN, MOVIES, -1, 0), "'M' (1931)")
+    self.assertEqual(regex_capture_nth(PATTERN, MOVIES, -3, 'year'), '1941')
+    self.assertEqual(regex_capture_nth(PATTERN, MOVIES, 4, 'title'), None)
+    self.assertEqual(regex_capture_nth(PATTERN, MOVIES, 1, 'nope'), None)
+    self.assertEqual(regex_capture_nth(r'(a)|(b)', 'ab', 1, 2), None)
+    self.assertEqual(regex_capture_nth(r'(a)|(b)', 'ab', 2, 2), 'b')
+
+    with self.assertRaisesRegex(sqlite3.OperationalError, "n is 1-based"):
+      regex_capture_nth(PATTERN, MOVIES, 0, 'title')
+
+  def test_regex_capture_start(self):
+    regex_capture_start = lambda *args: db.execute("select regex_capture_start({args})".format(args=spread_args(args)), args).fetchone()[0]
+    PATTERN = r"'(?P<title>[^']+)'\s+\((?P<year>\d{4})\)"
+    EXAMPLE = "Not my favorite: 'Citizen Kane' (1941)."
+    self.assertEqual(regex_capture_start(PATTERN, EXAMPLE, 0), 17)
+    self.assertEqual(regex_capture_start(PATTERN, EXAMPLE, 1), 18)
+    self.assertEqual(regex_capture_start(PATTERN, EXAMPLE, 'year'), 33)
+    self.assertEqual(regex_capture_start(PATTERN, EXAMPLE, 'year', 'byte'), 33)
+    self.assertEqual(regex_capture_start(PATTERN, EXAMPLE, 'year', None), 33)
+    self.assertEqual(regex_capture_start(r'b', 'ééb', 0), 4)
+    self.assertEqual(regex_capture_start(r'b', 'ééb', 0, 'char'), 2)
+    self.assertEqual(regex_capture_start(PATTERN, EXAMPLE, 'nope'), None)
+    self.assertEqual(regex_capture_start(PATTERN, EXAMPLE, 3), None)
+    self.assertEqual(regex_capture_start(PATTERN, 'no match', 0), None)
+    self.assertEqual(regex_capture_start(r'(a)|(b)', 'b', 1), None)
+
+    with self.assertRaisesRegex(sqlite3.OperationalError, "unknown offset unit 'bytes', expected 'byte' or 'char'"):
+      regex_capture_start(PATTERN, EXAMPLE, 0, 'bytes')
+
+  def test_regex_capture_end(self):
+    regex_capture_end = lambda *args: db.execute("select regex_capture_end({args})".format(args=spread_args(args)), args).fetchone()[0]
+    self.assertEqual(regex_capture_end(r'(\d+)', 'ab 123 c', 1), 6)
+    self.assertEqual(regex_capture_end(r'(\d+)', 'éé 123 c', 1), 8)
+    self.assertEqual(regex_capture_end(r'(\d+)', 'éé 123 c', 1, 'char'), 6)
+    self.assertEqual(regex_capture_end(r'(é+)', 'aéé', 1, 'char'), 3)
+    self.assertEqual(regex_capture_end(r'(\d+)', 'abc', 1), None)
+
+  def test_regex_capture_span(self):
+    regex_capture_span = lambda *args: db.execute("select regex_capture_span({args})".format(args=spread_args(args)), args).fetchone()[0]
+    self.assertEqual(regex_capture_span(r'(?P<n>\d+)', 'ab 123 c', 'n'), '[3,6]')
+    self.assertEqual(regex_capture_span(r'(?P<n>\d+)', 'éb 123 c', 'n'), '[4,7]')
+    self.assertEqual(regex_capture_span(r'(?P<n>\d+)', 'éb 123 c', 'n', 'char'), '[3,6]')
+    self.assertEqual(regex_capture_span(r'x*', 'abc', 0), '[0,0]')
+    self.assertEqual(regex_capture_span(r'(?P<n>\d+)', 'abc', 'n'), None)
+
+    # with captures objects from regex_captures, offsets are into the whole text
+    self.assertEqual(
+      execute_all(
+        """
+          select
+            regex_capture_span(captures, 'word') as bytes,
+            regex_capture_span(captures, 'word', 'char') as chars,
+            regex_capture_start(captures, 1, 'char') as start,
+            regex_capture_end(captures, 0) as "end"
+          from regex_captures(?, ?)
+        """,
+        [r'#(?P<word>\w+)', 'é #ab ééé #cd']
+      ),
+      [
+        {'bytes': '[4,6]', 'chars': '[3,5]', 'start': 3, 'end': 6},
+        {'bytes': '[15,17]', 'chars': '[11,13]', 'start': 11, 'end': 17},
+      ]
+    )
+    self.assertEqual(
+      execute_all(
+        "select regex_capture_start(captures, 'nope') as a, regex_capture_start(captures, 2) as b from regex_captures('(a)(b)?', 'a')"
+      ),
+      [{'a': None, 'b': None}]
+    )
+    with self.assertRaisesRegex(sqlite3.OperationalError, "expected at most 3 arguments with a captures object"):
+      db.execute("select regex_capture_span(captures, 0, 'char', 1) from regex_captures('a', 'a')").fetchone()
+
+  def test_regex_captures_len(self):
+    regex_captures_len = lambda pattern: db.execute("select regex_captures_len(?)", [pattern]).fetchone()[0]
+    self.assertEqual(regex_captures_len('abc'), 1)
+    self.assertEqual(regex_captures_len('(a)(?:b)(?P<c>c)'), 3)
+    self.assertEqual(db.execute("select regex_captures_len(regex('(a)|(b)'))").fetchone()[0], 3)
+
+  def test_regex_group_index(self):
+    regex_group_index = lambda pattern, name: db.execute("select regex_group_index(?, ?)", [pattern, name]).fetchone()[0]
+    self.assertEqual(regex_group_index('(?P<a>x)(y)(?P<b>z)', 'a'), 1)
+    self.assertEqual(regex_group_index('(?P<a>x)(y)(?P<b>z)', 'b'), 3)
+    self.assertEqual(regex_group_index('(?P<a>x)(y)(?P<b>z)', 'c'), None)
+    self.assertEqual(db.execute("select regex_group_index(regex('(?P<a>x)'), 'a')").fetchone()[0], 1)
 
   def test_regex_captures(self):
     MOVIE_PATTERN = "'(?P<title>[^']+)'\s+\((?P<year>\d{4})\)"
@@ -384,6 +1361,143 @@ class TestRegex(unittest.TestCase):
       ]
     )
 
+  def test_regex_read_lines(self):
+    with tempfile.NamedTemporaryFile("w", suffix=".log", delete=False) as f:
+      f.write("GET /a 200\r\nPOST /b 500\n\nGET /c 404\n")
+      path = f.name
+    try:
+      self.assertEqual(
+        execute_all("select rowid, line_number, line, matched from regex_read_lines(?, ?)", [path, '\\b[45]\\d\\d$']),
+        [
+          {'rowid': 1, 'line_number': 1, 'line': 'GET /a 200', 'matched': 0},
+          {'rowid': 2, 'line_number': 2, 'line': 'POST /b 500', 'matched': 1},
+          {'rowid': 3, 'line_number': 3, 'line': '', 'matched': 0},
+          {'rowid': 4, 'line_number': 4, 'line': 'GET /c 404', 'matched': 1},
+        ]
+      )
+      self.assertEqual(
+        execute_all(
+          """select
+            line_number,
+            regex_capture(captures, 'method') as method,
+            regex_capture(captures, 'status') as status,
+            regex_capture_span(captures, 'status') as status_span
+          from regex_read_lines(?, regex(?), 1)
+          """,
+          [path, '(?P<method>[A-Z]+) \\S+ (?P<status>[45]\\d\\d)']
+        ),
+        [
+          {'line_number': 2, 'method': 'POST', 'status': '500', 'status_span': '[8,11]'},
+          {'line_number': 4, 'method': 'GET', 'status': '404', 'status_span': '[7,10]'},
+        ]
+      )
+      with self.assertRaisesRegex(sqlite3.OperationalError, "Error opening"):
+        execute_all("select * from regex_read_lines(?, ?)", [path + ".missing", 'a'])
+    finally:
+      os.remove(path)
+
+  def test_regex_grep(self):
+    with tempfile.TemporaryDirectory() as root:
+      os.makedirs(os.path.join(root, "src", "nested"))
+      with open(os.path.join(root, "src", "lib.rs"), "w") as f:
+        f.write("// TODO one\nfn main() {} // TODO two\n")
+      with open(os.path.join(root, "src", "nested", "mod.rs"), "w") as f:
+        f.write("nothing\n// TODO three\n")
+      with open(os.path.join(root, "README.md"), "w") as f:
+        f.write("TODO docs\n")
+      with open(os.path.join(root, "data.bin"), "wb") as f:
+        f.write(b"TODO\x00binary")
+
+      self.assertEqual(
+        execute_all("select rowid, * from regex_grep(?, ?)", ['TODO \\w+', root]),
+        [
+          {'rowid': 0, 'path': os.path.join(root, "README.md"), 'line_number': 1, 'line': 'TODO docs', 'start': 0, 'end': 9, 'match': 'TODO docs'},
+          {'rowid': 1, 'path': os.path.join(root, "src", "lib.rs"), 'line_number': 1, 'line': '// TODO one', 'start': 3, 'end': 11, 'match': 'TODO one'},
+          {'rowid': 2, 'path': os.path.join(root, "src", "lib.rs"), 'line_number': 2, 'line': 'fn main() {} // TODO two', 'start': 16, 'end': 24, 'match': 'TODO two'},
+          {'rowid': 3, 'path': os.path.join(root, "src", "nested", "mod.rs"), 'line_number': 2, 'line': '// TODO three', 'start': 3, 'end': 13, 'match': 'TODO three'},
+        ]
+      )
+      self.assertEqual(
+        execute_all("select line_number, match from regex_grep(regex(?), ?, ?)", ['TODO \\w+', root, '*.rs']),
+        [
+          {'line_number': 1, 'match': 'TODO one'},
+          {'line_number': 2, 'match': 'TODO two'},
+          {'line_number': 2, 'match': 'TODO three'},
+        ]
+      )
+      self.assertEqual(
+        execute_all("select match from regex_grep(?, ?, ?)", ['TODO \\w+', root, 'src/[a-m]*.rs']),
+        [
+          {'match': 'TODO one'},
+          {'match': 'TODO two'},
+        ]
+      )
+      # start and end are byte offsets into the line
+      with open(os.path.join(root, "notes.txt"), "w", encoding="utf-8") as f:
+        f.write("café TODO later\n")
+      self.assertEqual(
+        execute_all("select start, end, match from regex_grep(?, ?, ?)", ['TODO \\w+', root, '*.txt']),
+        [{'start': 6, 'end': 16, 'match': 'TODO later'}]
+      )
+      with self.assertRaisesRegex(sqlite3.OperationalError, "Error reading"):
+        execute_all("select * from regex_grep(?, ?)", ['a', os.path.join(root, "missing")])
+
+  def test_ac_set(self):
+    self.assertEqual(db.execute("select ac_set('foo', 'bar')").fetchone()[0], None)
+    self.assertEqual(db.execute("select ac_set_print(ac_set('foo', 'bar'))").fetchone()[0], '["foo","bar"]')
+    self.assertEqual(db.execute("select ac_set_print(ac_set())").fetchone()[0], '[]')
+    with self.assertRaisesRegex(sqlite3.OperationalError, "value is not an ac_set object"):
+      db.execute("select ac_set_print('foo')").fetchone()
+
+  def test_ac_set_with_options(self):
+    ac_find = lambda options, text: execute_all(
+      "select term_index, start, end, match from ac_find_all(ac_set_with_options(?, 'foo', 'foobar'), ?)",
+      [options, text]
+    )
+    self.assertEqual(ac_find('', 'foobar'), [{'term_index': 0, 'start': 0, 'end': 3, 'match': 'foo'}])
+    self.assertEqual(ac_find('l', 'foobar'), [{'term_index': 1, 'start': 0, 'end': 6, 'match': 'foobar'}])
+    self.assertEqual(ac_find('i', 'FOObar'), [{'term_index': 0, 'start': 0, 'end': 3, 'match': 'FOO'}])
+    self.assertEqual(ac_find('il', 'FOOBAR'), [{'term_index': 1, 'start': 0, 'end': 6, 'match': 'FOOBAR'}])
+    with self.assertRaisesRegex(sqlite3.OperationalError, "unknown ac_set option 'x', expected one of 'il'"):
+      ac_find('x', 'foo')
+
+  def test_ac_set_agg(self):
+    terms = json.dumps(['apple', 'banana', None, 'cherry'])
+    self.assertEqual(
+      db.execute("select ac_set_print(ac_set_agg(value)) from json_each(?)", [terms]).fetchone()[0],
+      '["apple","banana","cherry"]'
+    )
+    self.assertEqual(
+      execute_all(
+        "select term_index, match from ac_find_all((select ac_set_agg(value, 'i') from json_each(?)), 'Cherry and APPLE pie')",
+        [terms]
+      ),
+      [{'term_index': 2, 'match': 'Cherry'}, {'term_index': 0, 'match': 'APPLE'}]
+    )
+    self.assertEqual(db.execute("select ac_set_agg(value) from json_each('[]')").fetchone()[0], None)
+
+  def test_ac_set_print(self):
+    self.assertEqual(db.execute("select ac_set_print(ac_set('a', 'b', 'a'))").fetchone()[0], '["a","b","a"]')
+
+  def test_ac_is_match(self):
+    ac_is_match = lambda text: db.execute("select ac_is_match(ac_set('foo', 'bar'), ?)", [text]).fetchone()[0]
+    self.assertEqual(ac_is_match('xxbarxx'), 1)
+    self.assertEqual(ac_is_match('xxx'), 0)
+    self.assertEqual(ac_is_match('FOO'), 0)
+    self.assertEqual(db.execute("select ac_is_match(ac_set_with_options('i', 'foo'), 'FOO')").fetchone()[0], 1)
+
+  def test_ac_find_all(self):
+    self.assertEqual(
+      execute_all("select rowid, * from ac_find_all(ac_set('he', 'she', 'hers'), 'ushers said he')"),
+      [
+        {'rowid': 0, 'term_index': 1, 'start': 1, 'end': 4, 'match': 'she'},
+        {'rowid': 1, 'term_index': 0, 'start': 12, 'end': 14, 'match': 'he'},
+      ]
+    )
+    self.assertEqual(execute_all("select * from ac_find_all(ac_set('x'), 'abc')"), [])
+    with self.assertRaisesRegex(sqlite3.OperationalError, "value is not an ac_set object"):
+      db.execute("select * from ac_find_all('x', 'abc')").fetchall()
+
 
 class TestCoverage(unittest.TestCase):
   def test_coverage(self):